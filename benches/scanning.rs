@@ -0,0 +1,85 @@
+// Times scanning and translating synthetic programs of growing size
+// Run with cargo bench, fails if time per byte grows with the size of
+// the program, which is what a quadratic scanner looks like
+
+extern crate parser;
+
+use std::process;
+use std::time::{Duration, Instant};
+
+// Sizes of program to try, in KB
+const SIZES: &[usize] = &[64, 128, 256, 512, 1024];
+// How much slower per byte the largest program may be than the smallest
+const TOLERANCE: f64 = 3.0;
+const RUNS: usize = 3;
+
+// A program of roughly kb kilobytes, using every kind of token
+fn program(kb: usize) -> String {
+    let mut source = "# synthetic program\nvar i x y z rav\nx := 1 y := 2 z := 3\n".to_string();
+    let mut chunk = 0;
+    while source.len() < kb * 1024 {
+        source.push_str(&format!("# chunk {}\n\
+                                  x := x + y * 2 - z / 3\n\
+                                  if x > y -> print x [] x < y -> y := y + 1 else -> z := 0 fi\n\
+                                  do z < 10 -> z := z + 1 od\n\
+                                  fa i := 1 to {} st i /= 2 -> var w rav w := i print w af\n",
+                                 chunk,
+                                 chunk));
+        chunk += 1;
+    }
+    source
+}
+
+// Fastest of a few runs of f
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn ns_per_byte(elapsed: Duration, bytes: usize) -> f64 {
+    elapsed.as_secs_f64() * 1e9 / bytes as f64
+}
+
+fn main() {
+    println!("{:>8} {:>12} {:>10} {:>12} {:>10}",
+             "size",
+             "tokenize",
+             "ns/byte",
+             "translate",
+             "ns/byte");
+    let mut per_byte = vec![];
+    for &kb in SIZES {
+        let source = program(kb);
+        let tokenize = time(|| {
+            parser::tokenize(&source).expect("synthetic programs scan");
+        });
+        let translate = time(|| {
+            let output = parser::translate_str(&source, parser::Options::default());
+            assert!(output.is_ok(), "synthetic programs translate");
+        });
+        println!("{:>6}KB {:>10.2}ms {:>10.2} {:>10.2}ms {:>10.2}",
+                 kb,
+                 tokenize.as_secs_f64() * 1e3,
+                 ns_per_byte(tokenize, source.len()),
+                 translate.as_secs_f64() * 1e3,
+                 ns_per_byte(translate, source.len()));
+        // Only scanning is held to being linear, translating is shown for comparison
+        per_byte.push(ns_per_byte(tokenize, source.len()));
+    }
+
+    let (first, last) = (per_byte[0], per_byte[per_byte.len() - 1]);
+    if last > first * TOLERANCE {
+        eprintln!("scanning is not linear, {:.2}ns/byte at {}KB but {:.2}ns/byte at {}KB",
+                  first,
+                  SIZES[0],
+                  last,
+                  SIZES[SIZES.len() - 1]);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,336 @@
+use codegen::{Backend, InitPolicy, ModPolicy};
+use parser::INT_BITS;
+use parser::ast::{BinOp, Block, Decl, Expr, ExprKind, GuardedCommands, Ident, Interner, Program,
+                  UnOp};
+
+use std::io;
+use std::io::Write;
+
+// Translates a Program into a single C main()
+// Each variable a becomes x_a, and when trapping reads of unassigned
+// variables u_a as well, which holds the line a was declared on until a
+// is first assigned and 0 from then on
+// Anything C has no operator for is a call to a helper function, written
+// out ahead of main() if the program needs it
+pub struct CBackend<'a> {
+    out: &'a mut dyn Write,
+    // How the names in the program are spelt
+    names: &'a Interner,
+    init: InitPolicy,
+    modulo: ModPolicy,
+    // Helpers called so far
+    helpers: Vec<&'static str>,
+}
+
+// Called in place of reading a variable that has not been assigned
+const UNINITIALIZED: &str = "static int uninitialized(const char *name, int line)
+{
+fprintf(stderr, \"uninitialized variable %s declared on line %d\\n\", name, line);
+exit(1);
+}
+";
+
+// In unsigned, so that overflow wraps around rather than being undefined
+const SQUARE: &str = "static int square(int x)
+{
+unsigned int u = x;
+return (int)(u * u);
+}
+";
+
+// Newton's method, rounding down, in long long so x / r + r cannot overflow
+const SQUARE_ROOT: &str = "static int square_root(int x, int line)
+{
+long long r, next;
+if (x < 0) {
+fprintf(stderr, \"square root of negative number %d on line %d\\n\", x, line);
+exit(1);
+}
+if (x < 2) {
+return x;
+}
+r = x;
+next = (r + x / r) / 2;
+while (next < r) {
+r = next;
+next = (r + x / r) / 2;
+}
+return (int)r;
+}
+";
+
+// C's % leaves the sign of x, moving a remainder of the other sign to that
+// of y rounds the quotient down instead of towards 0
+const FLOOR_MOD: &str = "static int floor_mod(int x, int y)
+{
+int r = x % y;
+if (r != 0 && (r < 0) != (y < 0)) {
+r += y;
+}
+return r;
+}
+";
+
+// Squaring and multiplying, in unsigned so that overflow wraps around
+// rather than being undefined
+const POWER: &str = "static int power(int x, int n, int line)
+{
+unsigned int result = 1, base = x;
+if (n < 0) {
+fprintf(stderr, \"negative exponent %d on line %d\\n\", n, line);
+exit(1);
+}
+while (n > 0) {
+if (n & 1) {
+result *= base;
+}
+base *= base;
+n >>= 1;
+}
+return (int)result;
+}
+";
+
+// Every helper, in the order they are written out
+const HELPERS: &[&str] = &[UNINITIALIZED, SQUARE, SQUARE_ROOT, FLOOR_MOD, POWER];
+
+impl<'a> CBackend<'a> {
+    pub fn new(out: &'a mut dyn Write,
+               names: &'a Interner,
+               init: InitPolicy,
+               modulo: ModPolicy)
+               -> CBackend<'a> {
+        CBackend {
+            out,
+            names,
+            init,
+            modulo,
+            helpers: vec![],
+        }
+    }
+
+    // How var is spelt
+    fn name(&self, var: &Ident) -> &'a str {
+        self.names.name(var.name)
+    }
+
+    // The program is going to call helper
+    fn need(&mut self, helper: &'static str) {
+        if !self.helpers.contains(&helper) {
+            self.helpers.push(helper);
+        }
+    }
+
+    // Note that var now has a value
+    fn assigned(&mut self, var: &Ident) -> io::Result<()> {
+        match self.init == InitPolicy::Trap {
+            true => write!(self.out, "u_{}=0;", self.name(var)),
+            false => Ok(()),
+        }
+    }
+
+    // if (g1) {...} else if (g2) {...} else {...}
+    fn guarded_commands(&mut self, gcs: &GuardedCommands) -> io::Result<()> {
+        for (i, gc) in gcs.arms.iter().enumerate() {
+            if i > 0 {
+                write!(self.out, "else ")?;
+            }
+            write!(self.out, "if( ")?;
+            self.expr(&gc.guard)?;
+            write!(self.out, " )")?;
+            self.commands(&gc.body)?;
+        }
+        if let Some(ref otherwise) = gcs.otherwise {
+            write!(self.out, "else")?;
+            self.commands(otherwise)?;
+        }
+        Ok(())
+    }
+
+    fn commands(&mut self, block: &Block) -> io::Result<()> {
+        writeln!(self.out, "{{")?;
+        self.block(block)?;
+        writeln!(self.out, "}}")
+    }
+}
+
+impl<'a> Backend for CBackend<'a> {
+    fn program(&mut self, program: &Program) -> io::Result<()> {
+        // main() goes first, to find out which helpers it calls
+        let mut main = vec![];
+        let helpers = {
+            let mut backend = CBackend::new(&mut main, self.names, self.init, self.modulo);
+            backend.block(&program.block)?;
+            backend.helpers
+        };
+        writeln!(self.out, "#include <stdio.h>")?;
+        if !helpers.is_empty() {
+            writeln!(self.out, "#include <stdlib.h>")?;
+        }
+        writeln!(self.out)?;
+        for helper in HELPERS.iter().filter(|helper| helpers.contains(helper)) {
+            writeln!(self.out, "{}", helper)?;
+        }
+        writeln!(self.out, "int main()\n{{")?;
+        self.out.write_all(&main)?;
+        writeln!(self.out, "return 0;\n}}")
+    }
+
+    fn decl(&mut self, decl: &Decl) -> io::Result<()> {
+        let name = self.name(&decl.name);
+        match self.init {
+            InitPolicy::Sentinel => writeln!(self.out, "int x_{}=-12345;", name),
+            InitPolicy::Zero => writeln!(self.out, "int x_{}=0;", name),
+            InitPolicy::Trap => {
+                writeln!(self.out,
+                         "int x_{}=0; int u_{}={};",
+                         name,
+                         name,
+                         decl.name.span.line)
+            }
+        }
+    }
+
+    fn assign(&mut self, target: &Ident, value: &Expr) -> io::Result<()> {
+        write!(self.out, "x_{}=( ", self.name(target))?;
+        self.expr(value)?;
+        write!(self.out, " );")?;
+        self.assigned(target)?;
+        writeln!(self.out)
+    }
+
+    fn eif(&mut self, gcs: &GuardedCommands) -> io::Result<()> {
+        writeln!(self.out)?;
+        self.guarded_commands(gcs)
+    }
+
+    fn edo(&mut self, gcs: &GuardedCommands) -> io::Result<()> {
+        writeln!(self.out, "while(1){{")?;
+        self.guarded_commands(gcs)?;
+        writeln!(self.out, "else {{ break; }}")?;
+        writeln!(self.out, "}}\n")
+    }
+
+    fn fa(&mut self,
+          var: &Ident,
+          from: &Expr,
+          to: &Expr,
+          st: Option<&Expr>,
+          body: &Block)
+          -> io::Result<()> {
+        let name = self.name(var);
+        write!(self.out, "for( x_{} = ( ", name)?;
+        self.expr(from)?;
+        write!(self.out, " )")?;
+        if self.init == InitPolicy::Trap {
+            write!(self.out, ", u_{} = 0", name)?;
+        }
+        write!(self.out, "; x_{} <= ( ", name)?;
+        self.expr(to)?;
+        writeln!(self.out, " ); x_{}++ )", name)?;
+        if let Some(st) = st {
+            write!(self.out, "if( ")?;
+            self.expr(st)?;
+            write!(self.out, " )")?;
+        }
+        self.commands(body)
+    }
+
+    fn print(&mut self, expr: &Expr) -> io::Result<()> {
+        write!(self.out, "printf(\"%d\\n\", ( ")?;
+        self.expr(expr)?;
+        writeln!(self.out, " ));")
+    }
+
+    // Fully parenthesize so C precedence never matters
+    fn expr(&mut self, expr: &Expr) -> io::Result<()> {
+        match expr.kind {
+            ExprKind::Var(ref id) if self.init == InitPolicy::Trap => {
+                self.need(UNINITIALIZED);
+                let name = self.name(id);
+                write!(self.out,
+                       "( u_{} ? uninitialized(\"{}\", u_{}) : x_{} )",
+                       name,
+                       name,
+                       name,
+                       name)
+            }
+            ExprKind::Var(ref id) => write!(self.out, "x_{}", self.name(id)),
+            // C has no literal for the most negative int, only minus the
+            // largest positive one
+            ExprKind::Num(num) if num == -(1 << (INT_BITS - 1)) => {
+                write!(self.out, "( {} - 1 )", num + 1)
+            }
+            ExprKind::Num(num) => write!(self.out, "{}", num),
+            ExprKind::Binary(BinOp::Mod, ref lhs, ref rhs) if self.modulo == ModPolicy::Floor => {
+                self.need(FLOOR_MOD);
+                write!(self.out, "floor_mod( ")?;
+                self.expr(lhs)?;
+                write!(self.out, ", ")?;
+                self.expr(rhs)?;
+                write!(self.out, " )")
+            }
+            ExprKind::Binary(BinOp::Pow, ref lhs, ref rhs) => {
+                self.need(POWER);
+                write!(self.out, "power( ")?;
+                self.expr(lhs)?;
+                write!(self.out, ", ")?;
+                self.expr(rhs)?;
+                write!(self.out, ", {} )", expr.span.line)
+            }
+            ExprKind::Binary(op, ref lhs, ref rhs) => {
+                write!(self.out, "( ")?;
+                self.expr(lhs)?;
+                write!(self.out, " {} ", binop(op))?;
+                self.expr(rhs)?;
+                write!(self.out, " )")
+            }
+            ExprKind::Unary(UnOp::Neg, ref operand) => {
+                write!(self.out, "( - ")?;
+                self.expr(operand)?;
+                write!(self.out, " )")
+            }
+            ExprKind::Unary(UnOp::Not, ref operand) => {
+                write!(self.out, "( ! ")?;
+                self.expr(operand)?;
+                write!(self.out, " )")
+            }
+            ExprKind::Unary(UnOp::Plus, ref operand) => {
+                write!(self.out, "( + ")?;
+                self.expr(operand)?;
+                write!(self.out, " )")
+            }
+            ExprKind::Unary(UnOp::Square, ref operand) => {
+                self.need(SQUARE);
+                write!(self.out, "square( ")?;
+                self.expr(operand)?;
+                write!(self.out, " )")
+            }
+            ExprKind::Unary(UnOp::Sqrt, ref operand) => {
+                self.need(SQUARE_ROOT);
+                write!(self.out, "square_root( ")?;
+                self.expr(operand)?;
+                write!(self.out, ", {} )", expr.span.line)
+            }
+        }
+    }
+}
+
+fn binop(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Or => "||",
+        BinOp::And => "&&",
+        BinOp::Eq => "==",
+        BinOp::Ne => "!=",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "<=",
+        BinOp::Ge => ">=",
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Mod => "%",
+        BinOp::Pow => unreachable!("** is a call to power()"),
+    }
+}
@@ -0,0 +1,278 @@
+use codegen::Backend;
+use parser::ast::{BinOp, Block, Decl, Expr, ExprKind, GuardedCommands, Ident, Interner, Program,
+                  UnOp};
+
+use std::io;
+use std::io::Write;
+
+// Pretty prints a Program back out as E source
+// Handy for checking what the parser actually understood
+pub struct EBackend<'a> {
+    out: &'a mut dyn Write,
+    // How the names in the program are spelt
+    names: &'a Interner,
+    indent: usize,
+}
+
+impl<'a> EBackend<'a> {
+    pub fn new(out: &'a mut dyn Write, names: &'a Interner) -> EBackend<'a> {
+        EBackend {
+            out,
+            names,
+            indent: 0,
+        }
+    }
+
+    // How var is spelt
+    fn name(&self, var: &Ident) -> &'a str {
+        self.names.name(var.name)
+    }
+
+    fn line_start(&mut self) -> io::Result<()> {
+        for _ in 0..self.indent {
+            write!(self.out, "    ")?;
+        }
+        Ok(())
+    }
+
+    // guarded_command { "[]" guarded_command } [ "else" commands ]
+    fn guarded_commands(&mut self, gcs: &GuardedCommands) -> io::Result<()> {
+        for (i, gc) in gcs.arms.iter().enumerate() {
+            if i > 0 {
+                self.line_start()?;
+                write!(self.out, "[] ")?;
+            }
+            self.expr(&gc.guard)?;
+            self.commands(&gc.body)?;
+        }
+        if let Some(ref otherwise) = gcs.otherwise {
+            self.line_start()?;
+            write!(self.out, "else")?;
+            self.commands(otherwise)?;
+        }
+        Ok(())
+    }
+
+    // "->" block, with the block indented on the following lines
+    fn commands(&mut self, block: &Block) -> io::Result<()> {
+        writeln!(self.out, " ->")?;
+        self.indent += 1;
+        self.block(block)?;
+        self.indent -= 1;
+        Ok(())
+    }
+
+    // Parenthesize an operand only when the grammar would otherwise
+    // group it differently
+    fn operand(&mut self, expr: &Expr, parent: BinOp, right: bool) -> io::Result<()> {
+        let parens = match expr.kind {
+            ExprKind::Binary(op, _, _) => {
+                let (child, parent) = (precedence(op), precedence(parent));
+                child < parent ||
+                (child == parent && (right != (child == POWER) || child == RELOP))
+            }
+            // - a ** b is - (a ** b)
+            ExprKind::Unary(UnOp::Neg, _) |
+            ExprKind::Unary(UnOp::Plus, _) if parent == BinOp::Pow && !right => true,
+            // As would a negative number, which is written with a minus
+            ExprKind::Num(num) if num < 0 => parent == BinOp::Pow && !right,
+            // Only and and or can take a not without it taking them in too
+            ExprKind::Unary(UnOp::Not, _) => precedence(parent) > precedence(BinOp::And),
+            // Would otherwise take in everything after it
+            _ => takes_rest(expr),
+        };
+        if parens {
+            write!(self.out, "(")?;
+            self.expr(expr)?;
+            write!(self.out, ")")
+        } else {
+            self.expr(expr)
+        }
+    }
+
+    // Where the grammar wants an expression rather than a condition, and
+    // and or and not have to be in parentheses
+    fn value(&mut self, expr: &Expr) -> io::Result<()> {
+        match is_condition(expr) {
+            true => {
+                write!(self.out, "(")?;
+                self.expr(expr)?;
+                write!(self.out, ")")
+            }
+            false => self.expr(expr),
+        }
+    }
+}
+
+impl<'a> Backend for EBackend<'a> {
+    fn program(&mut self, program: &Program) -> io::Result<()> {
+        self.block(&program.block)
+    }
+
+    fn block(&mut self, block: &Block) -> io::Result<()> {
+        if !block.decls.is_empty() {
+            self.line_start()?;
+            write!(self.out, "var")?;
+            for decl in &block.decls {
+                self.decl(decl)?;
+            }
+            writeln!(self.out, " rav")?;
+        }
+        for stmt in &block.stmts {
+            self.line_start()?;
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn decl(&mut self, decl: &Decl) -> io::Result<()> {
+        write!(self.out, " {}", self.name(&decl.name))
+    }
+
+    fn assign(&mut self, target: &Ident, value: &Expr) -> io::Result<()> {
+        write!(self.out, "{} := ", self.name(target))?;
+        self.value(value)?;
+        writeln!(self.out)
+    }
+
+    fn eif(&mut self, gcs: &GuardedCommands) -> io::Result<()> {
+        write!(self.out, "if ")?;
+        self.guarded_commands(gcs)?;
+        self.line_start()?;
+        writeln!(self.out, "fi")
+    }
+
+    fn edo(&mut self, gcs: &GuardedCommands) -> io::Result<()> {
+        write!(self.out, "do ")?;
+        self.guarded_commands(gcs)?;
+        self.line_start()?;
+        writeln!(self.out, "od")
+    }
+
+    fn fa(&mut self,
+          var: &Ident,
+          from: &Expr,
+          to: &Expr,
+          st: Option<&Expr>,
+          body: &Block)
+          -> io::Result<()> {
+        write!(self.out, "fa {} := ", self.name(var))?;
+        self.value(from)?;
+        write!(self.out, " to ")?;
+        self.value(to)?;
+        if let Some(st) = st {
+            write!(self.out, " st ")?;
+            self.expr(st)?;
+        }
+        self.commands(body)?;
+        self.line_start()?;
+        writeln!(self.out, "af")
+    }
+
+    fn print(&mut self, expr: &Expr) -> io::Result<()> {
+        write!(self.out, "print ")?;
+        self.value(expr)?;
+        writeln!(self.out)
+    }
+
+    fn expr(&mut self, expr: &Expr) -> io::Result<()> {
+        match expr.kind {
+            ExprKind::Var(ref id) => write!(self.out, "{}", self.name(id)),
+            ExprKind::Num(ref num) => write!(self.out, "{}", num),
+            ExprKind::Binary(op, ref lhs, ref rhs) => {
+                self.operand(lhs, op, false)?;
+                write!(self.out, " {} ", binop(op))?;
+                self.operand(rhs, op, true)
+            }
+            ExprKind::Unary(op, ref operand) => {
+                write!(self.out, "{}", unop(op))?;
+                match op {
+                    UnOp::Square | UnOp::Sqrt => self.value(operand),
+                    UnOp::Neg | UnOp::Plus => {
+                        match operand.kind {
+                            ExprKind::Binary(..) |
+                            ExprKind::Unary(UnOp::Not, _) => {
+                                write!(self.out, "(")?;
+                                self.expr(operand)?;
+                                write!(self.out, ")")
+                            }
+                            _ => self.expr(operand),
+                        }
+                    }
+                    UnOp::Not => {
+                        match operand.kind {
+                            ExprKind::Binary(BinOp::And, _, _) |
+                            ExprKind::Binary(BinOp::Or, _, _) => {
+                                write!(self.out, "(")?;
+                                self.expr(operand)?;
+                                write!(self.out, ")")
+                            }
+                            _ => self.expr(operand),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+const RELOP: u8 = 2;
+const POWER: u8 = 5;
+
+// or binds loosest, relop does not chain, ** binds tightest and groups to
+// the right
+fn precedence(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => 0,
+        BinOp::And => 1,
+        BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => RELOP,
+        BinOp::Add | BinOp::Sub => 3,
+        BinOp::Mul | BinOp::Div | BinOp::Mod => 4,
+        BinOp::Pow => POWER,
+    }
+}
+
+// Whether expr can only be parsed as a condition
+fn is_condition(expr: &Expr) -> bool {
+    matches!(expr.kind,
+             ExprKind::Binary(BinOp::And, _, _) | ExprKind::Binary(BinOp::Or, _, _) |
+             ExprKind::Unary(UnOp::Not, _))
+}
+
+// Whether expr ends in a ^ or @, which would take in whatever followed it
+fn takes_rest(expr: &Expr) -> bool {
+    match expr.kind {
+        ExprKind::Unary(UnOp::Square, _) | ExprKind::Unary(UnOp::Sqrt, _) => true,
+        ExprKind::Unary(_, ref operand) => takes_rest(operand),
+        _ => false,
+    }
+}
+
+fn unop(op: UnOp) -> &'static str {
+    match op {
+        UnOp::Square => "^",
+        UnOp::Sqrt => "@",
+        UnOp::Neg => "-",
+        UnOp::Plus => "+",
+        UnOp::Not => "not ",
+    }
+}
+
+fn binop(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Or => "or",
+        BinOp::And => "and",
+        BinOp::Eq => "=",
+        BinOp::Ne => "/=",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "<=",
+        BinOp::Ge => ">=",
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Mod => "mod",
+        BinOp::Pow => "**",
+    }
+}
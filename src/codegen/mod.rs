@@ -0,0 +1,132 @@
+// Code generation passes over the syntax tree built by the Parser
+// Each target language is a Backend, chosen at runtime by name
+
+pub mod c;
+pub mod e;
+
+use parser::ast::{Block, Decl, Expr, GuardedCommands, Ident, Interner, Program, Stmt, StmtKind};
+
+use std::io;
+use std::io::Write;
+
+// Names accepted by backend_for, the first is the default
+pub const TARGETS: &[&str] = &["c", "e"];
+
+// What a variable holds before it is first assigned
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InitPolicy {
+    // -12345, as the course always had it
+    #[default]
+    Sentinel,
+    Zero,
+    // Reading it stops the program, saying which variable it was
+    Trap,
+}
+
+// The name of each InitPolicy, as given on the command line
+pub const INIT_POLICIES: &[&str] = &["sentinel", "zero", "trap"];
+
+impl InitPolicy {
+    // One of INIT_POLICIES, None for anything else
+    pub fn from_name(name: &str) -> Option<InitPolicy> {
+        match name {
+            "sentinel" => Some(InitPolicy::Sentinel),
+            "zero" => Some(InitPolicy::Zero),
+            "trap" => Some(InitPolicy::Trap),
+            _ => None,
+        }
+    }
+}
+
+// What a mod b gives when a or b is negative
+// Either way the result is 0 or smaller than b in size
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ModPolicy {
+    // Takes the sign of a, as C's % does, so that with / rounding towards
+    // 0 a = a / b * b + a mod b, e.g. -7 mod 3 is -1
+    #[default]
+    Trunc,
+    // Takes the sign of b, the remainder of rounding a / b down, e.g.
+    // -7 mod 3 is 2 and 7 mod -3 is -2
+    Floor,
+}
+
+// The name of each ModPolicy, as given on the command line
+pub const MOD_POLICIES: &[&str] = &["trunc", "floor"];
+
+impl ModPolicy {
+    // One of MOD_POLICIES, None for anything else
+    pub fn from_name(name: &str) -> Option<ModPolicy> {
+        match name {
+            "trunc" => Some(ModPolicy::Trunc),
+            "floor" => Some(ModPolicy::Floor),
+            _ => None,
+        }
+    }
+}
+
+// A visitor over the syntax tree that writes out a translation
+// Backends only need to say how each construct looks, walking the
+// tree is handled by block and statement unless they care to override
+pub trait Backend {
+    fn program(&mut self, program: &Program) -> io::Result<()>;
+
+    fn decl(&mut self, decl: &Decl) -> io::Result<()>;
+
+    fn assign(&mut self, target: &Ident, value: &Expr) -> io::Result<()>;
+
+    fn eif(&mut self, gcs: &GuardedCommands) -> io::Result<()>;
+
+    fn edo(&mut self, gcs: &GuardedCommands) -> io::Result<()>;
+
+    fn fa(&mut self,
+          var: &Ident,
+          from: &Expr,
+          to: &Expr,
+          st: Option<&Expr>,
+          body: &Block)
+          -> io::Result<()>;
+
+    fn print(&mut self, expr: &Expr) -> io::Result<()>;
+
+    fn expr(&mut self, expr: &Expr) -> io::Result<()>;
+
+    fn block(&mut self, block: &Block) -> io::Result<()> {
+        for decl in &block.decls {
+            self.decl(decl)?;
+        }
+        for stmt in &block.stmts {
+            self.statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> io::Result<()> {
+        match stmt.kind {
+            StmtKind::Assign { ref target, ref value } => self.assign(target, value),
+            StmtKind::If(ref gcs) => self.eif(gcs),
+            StmtKind::Do(ref gcs) => self.edo(gcs),
+            StmtKind::Fa { ref var, ref from, ref to, ref st, ref body } => {
+                self.fa(var, from, to, st.as_ref(), body)
+            }
+            StmtKind::Print(ref expr) => self.print(expr),
+        }
+    }
+}
+
+// Look up the backend for a target name, writing its output to out
+// names spells out the Symbols of the programs it is given, which are those
+// of the Program they were parsed into
+// init and modulo only matter to some targets
+pub fn backend_for<'a>(target: &str,
+                       names: &'a Interner,
+                       init: InitPolicy,
+                       modulo: ModPolicy,
+                       out: &'a mut dyn Write)
+                       -> Option<Box<dyn Backend + 'a>> {
+    match target {
+        "c" => Some(Box::new(c::CBackend::new(out, names, init, modulo))),
+        "e" => Some(Box::new(e::EBackend::new(out, names))),
+        _ => None,
+    }
+}
@@ -0,0 +1,116 @@
+// Translator from E to C (or back to E), usable without touching the disk
+// The parser binary is a thin command line wrapper around translate_str
+
+// Diagnostics are only ever built on the error path, so carrying them by
+// value through Result costs nothing worth boxing for
+#![allow(clippy::result_large_err)]
+
+pub mod codegen;
+pub mod json;
+pub mod parser;
+
+pub use parser::Parser;
+pub use parser::ast::Program;
+pub use parser::diagnostic::Diagnostic;
+pub use parser::interner::{Interner, Symbol};
+pub use parser::scanner::Scanner;
+pub use parser::token::{Span, Token, TokenType};
+
+use parser::diagnostic;
+use parser::symbol_table::ReportFormat;
+
+// How to translate
+pub struct Options {
+    // One of codegen::TARGETS
+    pub target: String,
+    // Give up once this many errors have been found
+    pub max_errors: usize,
+    pub init: codegen::InitPolicy,
+    pub modulo: codegen::ModPolicy,
+    pub report_format: ReportFormat,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            target: codegen::TARGETS[0].to_string(),
+            max_errors: parser::MAX_ERRORS,
+            init: codegen::InitPolicy::default(),
+            modulo: codegen::ModPolicy::default(),
+            report_format: ReportFormat::default(),
+        }
+    }
+}
+
+// Everything a successful translation produces
+pub struct Output {
+    // The program in the target language
+    pub code: String,
+    // Problems that did not stop translation, in source order
+    pub warnings: Vec<Diagnostic>,
+    // Every variable along with where it is used and assigned
+    pub report: String,
+}
+
+// Translate E source into options.target
+// On failure every error and warning found is returned, in source order
+pub fn translate_str(source: &str, options: Options) -> Result<Output, Vec<Diagnostic>> {
+    let mut parser = Parser::from_source(source);
+    parser.set_max_errors(options.max_errors);
+    let result = parser.parse();
+
+    let mut diagnostics = parser.warnings().to_vec();
+    if let Err(ref errors) = result {
+        diagnostics.extend(errors.iter().cloned());
+    }
+    diagnostics.sort_by_key(|d| d.span.start);
+    let program = match result {
+        Ok(program) => program,
+        Err(_) => return Err(diagnostics),
+    };
+
+    let mut code = vec![];
+    match codegen::backend_for(&options.target,
+                               &program.names,
+                               options.init,
+                               options.modulo,
+                               &mut code) {
+        Some(mut backend) => backend.program(&program).expect("writing to memory cannot fail"),
+        None => {
+            return Err(vec![Diagnostic::error(diagnostic::UNKNOWN_TARGET,
+                                              format!("Unknown target {}, expected one of {}",
+                                                      options.target,
+                                                      codegen::TARGETS.join(", ")),
+                                              Span::default())])
+        }
+    }
+    let mut report = vec![];
+    parser.symbol_table()
+        .report(options.report_format, &program.names, &mut report)
+        .expect("writing to memory cannot fail");
+
+    Ok(Output {
+        code: String::from_utf8(code).expect("backends only write str"),
+        warnings: diagnostics,
+        report: String::from_utf8(report).expect("the report is only written as str"),
+    })
+}
+
+// Just the syntax tree, with the default limit on errors
+pub fn parse_str(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    Parser::from_source(source).parse()
+}
+
+// Every token up to and including EOF, along with how the identifiers and
+// numbers among them are spelt
+// Characters outside the language come back as UNSUP tokens, only
+// malformed two character operators are errors
+pub fn tokenize(source: &str) -> Result<(Vec<Token>, Interner), Vec<Diagnostic>> {
+    let mut scanner = Scanner::from_source(source);
+    let tokens = scanner.by_ref().collect();
+    let errors = scanner.take_errors();
+    match errors.is_empty() {
+        true => Ok((tokens, scanner.into_names())),
+        false => Err(errors),
+    }
+}
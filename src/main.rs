@@ -1,8 +1,156 @@
+extern crate parser;
+
 use std::env;
-mod parser;
-use parser::Parser;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, IsTerminal, Read, Write};
+use std::process;
+use parser::{codegen, Diagnostic, Options, Span};
+use parser::parser::diagnostic;
+use parser::parser::render::Renderer;
+use parser::parser::sarif;
+use parser::parser::symbol_table::{ReportFormat, REPORT_FORMATS};
+
+fn usage() -> ! {
+    eprintln!("usage: parser [--target {}] [--max-errors N] [--color auto|always|never] \
+               [--diagnostics text|json|sarif] [-o out] [--report out] \
+               [--report-format {}] [--init {}] [--mod {}] file.e",
+              codegen::TARGETS.join("|"),
+              REPORT_FORMATS.join("|"),
+              codegen::INIT_POLICIES.join("|"),
+              codegen::MOD_POLICIES.join("|"));
+    process::exit(2);
+}
+
+// Write diagnostics to stderr, for people or in a format tools can read
+fn emit(diagnostics: &[Diagnostic], filename: &str, source: &str, format: &str, colour: bool) {
+    match format {
+        "json" => {
+            for d in diagnostics {
+                eprintln!("{}", d.to_json(filename));
+            }
+        }
+        "sarif" => eprintln!("{}", sarif::log(diagnostics, filename, source)),
+        _ => {
+            let renderer = Renderer::new(filename, source, colour);
+            for d in diagnostics {
+                eprintln!("{}", renderer.render(d));
+            }
+        }
+    }
+}
+
+// Where to send generated code or the report, "-" being stdout
+fn create(path: &str) -> io::Result<Box<dyn Write>> {
+    match path {
+        "-" => Ok(Box::new(io::stdout())),
+        _ => Ok(Box::new(BufWriter::new(File::create(path)?))),
+    }
+}
+
+// Report a problem that stops translation before there is any source to show
+fn fail(diagnostic: Diagnostic, filename: &str, format: &str, colour: bool) -> ! {
+    emit(&[diagnostic], filename, "", format, colour);
+    process::exit(1);
+}
 
 fn main() {
-    let mut parser = Parser::new(env::args().nth(1).unwrap());
-    parser.parse();
+    let mut options = Options::default();
+    let mut filename = None;
+    let mut colour = io::stderr().is_terminal();
+    let mut format = "text".to_string();
+    let mut output_path = "-".to_string();
+    let mut report = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "-t" | "--target" => options.target = args.next().unwrap_or_else(|| usage()),
+            "--max-errors" => {
+                options.max_errors = match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) if n > 0 => n,
+                    _ => usage(),
+                }
+            }
+            "--color" | "--colour" => {
+                colour = match args.next().as_ref().map(|c| c.as_ref()) {
+                    Some("always") => true,
+                    Some("never") => false,
+                    Some("auto") => io::stderr().is_terminal(),
+                    _ => usage(),
+                }
+            }
+            "-o" | "--output" => output_path = args.next().unwrap_or_else(|| usage()),
+            "--report" => report = Some(args.next().unwrap_or_else(|| usage())),
+            "--init" => {
+                let init = args.next().and_then(|i| codegen::InitPolicy::from_name(&i));
+                options.init = init.unwrap_or_else(|| usage())
+            }
+            "--mod" => {
+                let modulo = args.next().and_then(|m| codegen::ModPolicy::from_name(&m));
+                options.modulo = modulo.unwrap_or_else(|| usage())
+            }
+            "--report-format" => {
+                let format = args.next().and_then(|f| ReportFormat::from_name(&f));
+                options.report_format = format.unwrap_or_else(|| usage())
+            }
+            "--diagnostics" => {
+                format = match args.next() {
+                    Some(f) => {
+                        match f.as_ref() {
+                            "text" | "json" | "sarif" => f,
+                            _ => usage(),
+                        }
+                    }
+                    None => usage(),
+                }
+            }
+            _ if filename.is_none() => filename = Some(arg),
+            _ => usage(),
+        }
+    }
+    let filename = filename.unwrap_or_else(|| usage());
+    if !codegen::TARGETS.contains(&options.target.as_ref()) {
+        eprintln!("unknown target {}, expected one of {}",
+                  options.target,
+                  codegen::TARGETS.join(", "));
+        process::exit(2);
+    }
+
+    let mut source = String::new();
+    if let Err(e) = File::open(&filename).and_then(|mut file| file.read_to_string(&mut source)) {
+        fail(Diagnostic::error(diagnostic::IO_ERROR,
+                               format!("Could not open {} : {}", filename, e),
+                               Span::default()),
+             &filename,
+             &format,
+             colour);
+    }
+    let output = match parser::translate_str(&source, options) {
+        Ok(output) => output,
+        Err(diagnostics) => {
+            emit(&diagnostics, &filename, &source, &format, colour);
+            process::exit(1);
+        }
+    };
+    emit(&output.warnings, &filename, &source, &format, colour);
+
+    // Code, and the report if asked for, only once the program is known to be good
+    let mut sinks = vec![(output_path, output.code)];
+    if let Some(report) = report {
+        sinks.push((report, output.report));
+    }
+    for (path, text) in sinks {
+        if let Err(e) = create(&path).and_then(|mut out| {
+            out.write_all(text.as_bytes())?;
+            out.flush()
+        }) {
+            fail(Diagnostic::error(diagnostic::IO_ERROR,
+                                   format!("Could not write {} : {}", path, e),
+                                   Span::default()),
+                 &filename,
+                 &format,
+                 colour);
+        }
+    }
 }
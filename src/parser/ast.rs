@@ -0,0 +1,134 @@
+// Typed syntax tree produced by the Parser
+// Every node remembers where in the source it came from, even if the
+// C translation itself never looks
+
+pub use super::interner::{Interner, Symbol};
+pub use super::token::Span;
+
+// program ::= block
+#[derive(Debug)]
+pub struct Program {
+    pub block: Block,
+    // How each Symbol in it is spelt
+    pub names: Interner,
+}
+
+// block ::= [declarations] statement_list
+#[derive(Debug)]
+pub struct Block {
+    pub decls: Vec<Decl>,
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+// A single variable introduced by "var" ... "rav"
+#[derive(Debug)]
+pub struct Decl {
+    pub name: Ident,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ident {
+    pub name: Symbol,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum StmtKind {
+    // id ":=" expression
+    Assign { target: Ident, value: Expr },
+    // "if" guarded_commands "fi"
+    If(GuardedCommands),
+    // "do" guarded_commands "od"
+    Do(GuardedCommands),
+    // "fa" id ":=" expression "to" expression ["st" condition] commands "af"
+    Fa {
+        var: Ident,
+        from: Expr,
+        to: Expr,
+        st: Option<Expr>,
+        body: Block,
+    },
+    // "print" expression
+    Print(Expr),
+}
+
+// guarded_command { "[]" guarded_command } [ "else" commands ]
+#[derive(Debug)]
+pub struct GuardedCommands {
+    pub arms: Vec<GuardedCommand>,
+    pub otherwise: Option<Block>,
+}
+
+// guarded_command ::= condition commands
+#[derive(Debug)]
+pub struct GuardedCommand {
+    pub guard: Expr,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum ExprKind {
+    Var(Ident),
+    // Negative only when a "-" came straight before the number
+    Num(i64),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    // "^" expression | "@" expression | "-" power | "+" power | "not" negation
+    Unary(UnOp, Box<Expr>),
+}
+
+// Prefix operators
+// "^" and "@" apply to the whole expression after them, "-" and "+" to
+// just the power after them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnOp {
+    // "^" x is x * x
+    Square,
+    // "@" x is the largest integer whose square is at most x, x must not
+    // be negative
+    Sqrt,
+    Neg,
+    Plus,
+    // 1 if x is 0, otherwise 0
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    // connectives, which only look at their right operand when the left
+    // one does not settle the result
+    Or,
+    And,
+    // relop
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    // addop
+    Add,
+    Sub,
+    // multop
+    Mul,
+    Div,
+    // The remainder of dividing, which for negative operands depends on
+    // the backend's codegen::ModPolicy
+    Mod,
+    // a ** b is a multiplied by itself b times, b must not be negative
+    Pow,
+}
@@ -0,0 +1,232 @@
+// Definite assignment, which finds variables read before anything has been
+// assigned to them, and so would print the value every variable starts as
+// Runs over the syntax tree of a program that parsed without errors
+
+use super::ast::{Block, Expr, ExprKind, GuardedCommands, Ident, Interner, Program, Stmt,
+                 StmtKind, Symbol};
+use super::diagnostic::{Diagnostic, MAYBE_UNASSIGNED, UNASSIGNED};
+use super::token::{Span, Token, TokenType};
+use std::collections::{HashMap, HashSet};
+
+// Which variables have been assigned by some point in the program
+// Variables are known by where they were declared
+#[derive(Clone, PartialEq)]
+struct State {
+    // On every path that reaches here
+    definitely: HashSet<usize>,
+    // On at least one path that reaches here
+    possibly: HashSet<usize>,
+}
+
+impl State {
+    fn new() -> State {
+        State {
+            definitely: HashSet::new(),
+            possibly: HashSet::new(),
+        }
+    }
+
+    fn assign(&mut self, var: usize) {
+        self.definitely.insert(var);
+        self.possibly.insert(var);
+    }
+
+    // Where two paths meet
+    fn join(&self, other: &State) -> State {
+        State {
+            definitely: self.definitely.intersection(&other.definitely).cloned().collect(),
+            possibly: self.possibly.union(&other.possibly).cloned().collect(),
+        }
+    }
+}
+
+struct Checker<'a> {
+    names: &'a Interner,
+    // Variables visible in each open block, innermost last
+    scopes: Vec<HashMap<Symbol, usize>>,
+    // Where each variable was declared, by its number
+    declared: HashMap<usize, Span>,
+    // Off while a loop is being worked out, so each read is reported once
+    reporting: bool,
+    // Every variable assigned anywhere in the program
+    assigned: HashSet<usize>,
+    // Along with the variable each is about
+    warnings: Vec<(usize, Diagnostic)>,
+}
+
+// Warnings for every read that may come before the variable is assigned
+// A variable that is never assigned at all has already been warned about
+// by the symbol table, so its reads are not
+pub fn check(program: &Program) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        names: &program.names,
+        scopes: vec![],
+        declared: HashMap::new(),
+        reporting: true,
+        assigned: HashSet::new(),
+        warnings: vec![],
+    };
+    checker.block(&program.block, State::new());
+    let assigned = checker.assigned;
+    checker.warnings
+        .into_iter()
+        .filter(|&(var, _)| assigned.contains(&var))
+        .map(|(_, warning)| warning)
+        .collect()
+}
+
+impl<'a> Checker<'a> {
+    fn block(&mut self, block: &Block, mut state: State) -> State {
+        let mut scope = HashMap::new();
+        for decl in &block.decls {
+            scope.insert(decl.name.name, decl.name.span.start);
+            self.declared.insert(decl.name.span.start, decl.name.span);
+        }
+        self.scopes.push(scope);
+        for stmt in &block.stmts {
+            state = self.statement(stmt, state);
+        }
+        // Its variables start again unassigned if the block is run again
+        for (_, var) in self.scopes.pop().unwrap() {
+            state.definitely.remove(&var);
+            state.possibly.remove(&var);
+        }
+        state
+    }
+
+    fn statement(&mut self, stmt: &Stmt, mut state: State) -> State {
+        match stmt.kind {
+            StmtKind::Assign { ref target, ref value } => {
+                self.read(value, &state);
+                if let Some(var) = self.resolve(target) {
+                    self.assigned.insert(var);
+                    state.assign(var);
+                }
+                state
+            }
+            StmtKind::Print(ref expr) => {
+                self.read(expr, &state);
+                state
+            }
+            // Without an else, no guard being true leaves everything as it was
+            StmtKind::If(ref gcs) => {
+                let after = self.guarded_commands(gcs, &state);
+                match gcs.otherwise {
+                    Some(_) => after,
+                    None => after.join(&state),
+                }
+            }
+            // Left once no guard is true, which may be before any body runs
+            StmtKind::Do(ref gcs) => self.repeat(state, |checker, head| {
+                checker.guarded_commands(gcs, head)
+            }),
+            // The control variable is assigned before the first test, even
+            // if the body never runs, and the bound is worked out again at
+            // each test, after that assignment
+            StmtKind::Fa { ref var, ref from, ref to, ref st, ref body } => {
+                self.read(from, &state);
+                if let Some(var) = self.resolve(var) {
+                    self.assigned.insert(var);
+                    state.assign(var);
+                }
+                self.read(to, &state);
+                self.repeat(state, |checker, head| {
+                    if let Some(ref st) = *st {
+                        checker.read(st, head);
+                    }
+                    checker.block(body, head.clone())
+                })
+            }
+        }
+    }
+
+    // Every guard is tested in state, the result is wherever any body
+    // (or the else) may leave things
+    fn guarded_commands(&mut self, gcs: &GuardedCommands, state: &State) -> State {
+        let mut after: Option<State> = None;
+        for gc in &gcs.arms {
+            self.read(&gc.guard, state);
+            let end = self.block(&gc.body, state.clone());
+            after = Some(after.map_or(end.clone(), |a| a.join(&end)));
+        }
+        if let Some(ref otherwise) = gcs.otherwise {
+            let end = self.block(otherwise, state.clone());
+            after = Some(after.map_or(end.clone(), |a| a.join(&end)));
+        }
+        after.unwrap_or_else(|| state.clone())
+    }
+
+    // A loop entered in entry whose body, run from the state at the top of
+    // the loop, is once. The loop may go round any number of times, so the
+    // top of the loop is worked out by running once until nothing changes,
+    // and then once more to report what is read there
+    fn repeat<F>(&mut self, entry: State, mut once: F) -> State
+        where F: FnMut(&mut Checker<'a>, &State) -> State
+    {
+        let reporting = self.reporting;
+        self.reporting = false;
+        let mut head = entry.clone();
+        loop {
+            let next = entry.join(&once(self, &head));
+            if next == head {
+                break;
+            }
+            head = next;
+        }
+        self.reporting = reporting;
+        once(self, &head);
+        head
+    }
+
+    // Which variable a name refers to, None if it is undeclared, which
+    // has already been reported
+    fn resolve(&self, ident: &Ident) -> Option<usize> {
+        self.scopes.iter().rev().filter_map(|scope| scope.get(&ident.name)).next().cloned()
+    }
+
+    fn read(&mut self, expr: &Expr, state: &State) {
+        match expr.kind {
+            ExprKind::Var(ref ident) => {
+                let var = match self.resolve(ident) {
+                    Some(var) => var,
+                    None => return,
+                };
+                if !self.reporting || state.definitely.contains(&var) {
+                    return;
+                }
+                let token = Token {
+                    typ: TokenType::ID(ident.name),
+                    span: ident.span,
+                };
+                let warning = match state.possibly.contains(&var) {
+                    true => {
+                        Diagnostic::warning_at(MAYBE_UNASSIGNED,
+                                               format!("Variable {} may be used before being \
+                                                        assigned",
+                                                       self.names.name(ident.name)),
+                                               &token,
+                                               self.names)
+                            .with_note("it is not assigned on every path that reaches here"
+                                .to_string())
+                    }
+                    false => {
+                        Diagnostic::warning_at(UNASSIGNED,
+                                               format!("Variable {} is definitely used before \
+                                                        assignment",
+                                                       self.names.name(ident.name)),
+                                               &token,
+                                               self.names)
+                    }
+                };
+                let warning = warning.with_label(self.declared[&var], "declared here".to_string());
+                self.warnings.push((var, warning));
+            }
+            ExprKind::Num(_) => {}
+            ExprKind::Binary(_, ref lhs, ref rhs) => {
+                self.read(lhs, state);
+                self.read(rhs, state);
+            }
+            ExprKind::Unary(_, ref operand) => self.read(operand, state),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+// Errors and warnings found while translating, reported to the user
+// instead of panicking
+
+use super::interner::Interner;
+use super::token::{Span, Token, TokenType};
+use json::Json;
+use std::fmt;
+
+// Codes identifying each kind of diagnostic
+pub const IO_ERROR: &str = "E000";
+pub const BAD_TOKEN: &str = "E001";
+pub const SYNTAX: &str = "E002";
+pub const UNDECLARED: &str = "E003";
+pub const MISPLACED_DECLARATION: &str = "E004";
+pub const JUNK: &str = "E005";
+pub const TOO_MANY_ERRORS: &str = "E006";
+pub const UNSUPPORTED: &str = "E007";
+pub const UNKNOWN_TARGET: &str = "E008";
+pub const BAD_NUMBER: &str = "E009";
+pub const NUMBER_RANGE: &str = "E010";
+pub const REDECLARED: &str = "W001";
+pub const UNUSED: &str = "W002";
+pub const NEVER_READ: &str = "W003";
+pub const NEVER_ASSIGNED: &str = "W004";
+pub const MAYBE_UNASSIGNED: &str = "W005";
+pub const UNASSIGNED: &str = "W006";
+pub const MIXED_TRUTH: &str = "W007";
+
+// What each code means, for tools that want a description of every rule
+pub const RULES: &[(&str, &str)] = &[(IO_ERROR, "A file could not be read or written"),
+                                     (BAD_TOKEN, "A two character operator is incomplete"),
+                                     (SYNTAX, "The program does not follow the grammar"),
+                                     (UNDECLARED, "A variable is used but not in scope"),
+                                     (MISPLACED_DECLARATION,
+                                      "A declaration follows the statements of its block"),
+                                     (JUNK, "Text follows the end of the program"),
+                                     (TOO_MANY_ERRORS, "Translation stopped after too many errors"),
+                                     (UNSUPPORTED, "A character is not part of the language"),
+                                     (UNKNOWN_TARGET, "There is no backend for the target"),
+                                     (BAD_NUMBER, "A number is not written correctly"),
+                                     (NUMBER_RANGE, "A number does not fit in an int"),
+                                     (REDECLARED, "A variable is declared twice in one block"),
+                                     (UNUSED, "A variable is declared but never used"),
+                                     (NEVER_READ, "A variable is assigned but never read"),
+                                     (NEVER_ASSIGNED, "A variable is read but never assigned"),
+                                     (MAYBE_UNASSIGNED,
+                                      "A variable may be read before it is assigned"),
+                                     (UNASSIGNED, "A variable is read before it is assigned"),
+                                     (MIXED_TRUTH,
+                                      "A truth value is used as a number, or the other way \
+                                       round")];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// Some other place in the source worth pointing at
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    // The token it is about as spelt in the source, None at the end of the
+    // file as well as when it is not about any one token
+    pub token: Option<String>,
+    pub labels: Vec<Label>,
+    // Extra explanation, and suggestions of what to do about it
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    // An error that is not tied to any particular token
+    // Line 0 is for errors outside the source altogether, like a missing file
+    pub fn error(code: &'static str, message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            span,
+            token: None,
+            labels: vec![],
+            notes: vec![],
+            help: vec![],
+        }
+    }
+
+    // An error caused by the given token, spelling out any identifier or
+    // number it holds from names
+    pub fn error_at(code: &'static str,
+                    message: String,
+                    token: &Token,
+                    names: &Interner)
+                    -> Diagnostic {
+        let spelling = match token.typ {
+            TokenType::EOF => None,
+            ref typ => Some(typ.spelling(names)),
+        };
+        Diagnostic {
+            token: spelling,
+            ..Diagnostic::error(code, message, token.span)
+        }
+    }
+
+    pub fn warning(code: &'static str, message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(code, message, span)
+        }
+    }
+
+    pub fn warning_at(code: &'static str,
+                      message: String,
+                      token: &Token,
+                      names: &Interner)
+                      -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error_at(code, message, token, names)
+        }
+    }
+
+    // Point at another span that helps explain this one
+    pub fn with_label(mut self, span: Span, message: String) -> Diagnostic {
+        self.labels.push(Label { span, message });
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Diagnostic {
+        self.help.push(help);
+        self
+    }
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+impl Span {
+    pub fn to_json(self) -> Json {
+        Json::Obj(vec![("start", Json::num(self.start)),
+                       ("end", Json::num(self.end)),
+                       ("line", Json::num(self.line)),
+                       ("column", Json::num(self.col))])
+    }
+}
+
+impl Diagnostic {
+    // One self contained object, as written on each line of --diagnostics json
+    pub fn to_json(&self, file: &str) -> Json {
+        let span = match self.span.line {
+            0 => Json::Null,
+            _ => self.span.to_json(),
+        };
+        let token = match self.token {
+            Some(ref token) => Json::str(token.clone()),
+            None => Json::Null,
+        };
+        let labels = self.labels
+            .iter()
+            .map(|label| {
+                Json::Obj(vec![("message", Json::str(label.message.clone())),
+                               ("span", label.span.to_json())])
+            })
+            .collect();
+        let strings = |v: &[String]| Json::Arr(v.iter().map(|s| Json::str(s.clone())).collect());
+        Json::Obj(vec![("severity", Json::str(self.severity.name())),
+                       ("code", Json::str(self.code)),
+                       ("message", Json::str(self.message.clone())),
+                       ("file", Json::str(file)),
+                       ("span", span),
+                       ("token", token),
+                       ("labels", Json::Arr(labels)),
+                       ("notes", strings(&self.notes)),
+                       ("help", strings(&self.help))])
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "ERROR"),
+            Severity::Warning => write!(f, "WARNING"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token {
+            Some(ref token) => {
+                write!(f,
+                       "[{}] line {}, column {}, at `{}`: {}",
+                       self.severity,
+                       self.span.line,
+                       self.span.col,
+                       token,
+                       self.message)
+            }
+            _ if self.span.line == 0 => write!(f, "[{}] {}", self.severity, self.message),
+            _ => {
+                write!(f,
+                       "[{}] line {}, column {}: {}",
+                       self.severity,
+                       self.span.line,
+                       self.span.col,
+                       self.message)
+            }
+        }
+    }
+}
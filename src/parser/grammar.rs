@@ -0,0 +1,94 @@
+// FIRST and FOLLOW sets of the E grammar, so that when the Parser finds
+// something it did not want it can say what it would have accepted
+
+use super::interner::{Interner, Symbol};
+use super::token::TokenType;
+use super::token::TokenType::*;
+
+// FIRST(statement)
+pub const STATEMENT: &[TokenType] = &[ID(Symbol::EMPTY), PRINT, IF, DO, FA];
+
+// FIRST(expression), which is FIRST(factor)
+pub const EXPRESSION: &[TokenType] = &[ID(Symbol::EMPTY), NUM(Symbol::EMPTY), LPAREN, SQUARE,
+                                       SQRT, MINUS, PLUS];
+
+// FIRST(condition)
+pub const CONDITION: &[TokenType] = &[ID(Symbol::EMPTY), NUM(Symbol::EMPTY), LPAREN, SQUARE,
+                                      SQRT, MINUS, PLUS, NOT];
+
+pub const CONNECTIVES: &[TokenType] = &[AND, OR];
+pub const RELOPS: &[TokenType] = &[EQ, NE, LT, GT, LE, GE];
+pub const ADDOPS: &[TokenType] = &[PLUS, MINUS];
+pub const MULTOPS: &[TokenType] = &[TIMES, DIVIDE, MOD, PERCENT];
+pub const POWEROPS: &[TokenType] = &[POWER];
+
+// What may follow the last guarded command of an if, before and after its
+// else has been seen, or of a do, which never has an else
+pub const MORE_GUARDS: &[TokenType] = &[BOX, ELSE];
+pub const MORE_LOOP_GUARDS: &[TokenType] = &[BOX];
+
+// MORE_GUARDS for the construct closed by closer
+pub fn more_guards(closer: &TokenType) -> &'static [TokenType] {
+    match *closer {
+        OD => MORE_LOOP_GUARDS,
+        _ => MORE_GUARDS,
+    }
+}
+
+// Every token that can continue an expression which has so far parsed
+// fine, i.e. FIRST of the operator of each level
+pub fn operators() -> Vec<TokenType> {
+    RELOPS.iter().chain(ADDOPS).chain(MULTOPS).chain(POWEROPS).cloned().collect()
+}
+
+// Anything that can follow a complete expression, together with more
+pub fn after_expression(more: &[TokenType]) -> Vec<TokenType> {
+    let mut set = operators();
+    set.extend(more.iter().cloned());
+    set
+}
+
+// Anything that can follow a complete condition, together with more
+pub fn after_condition(more: &[TokenType]) -> Vec<TokenType> {
+    let mut set = operators();
+    set.extend(CONNECTIVES.iter().cloned());
+    set.extend(more.iter().cloned());
+    set
+}
+
+// Spell out a set of tokens for a message, e.g. "`fi`, `[]` or `else`"
+// A set holding every operator is summed up as "an operator"
+pub fn describe(set: &[TokenType], names: &Interner) -> String {
+    let all_ops = operators().iter().all(|op| set.contains(op));
+    let mut parts: Vec<String> = vec![];
+    if all_ops {
+        parts.push("an operator".to_string());
+    }
+    for typ in set {
+        if all_ops && operators().contains(typ) {
+            continue;
+        }
+        parts.push(describe_one(typ, names));
+    }
+    match parts.len() {
+        0 => "nothing".to_string(),
+        1 => parts.remove(0),
+        n => format!("{} or {}", parts[..n - 1].join(", "), parts[n - 1]),
+    }
+}
+
+// Spell out the token actually found
+pub fn describe_found(typ: &TokenType, names: &Interner) -> String {
+    match *typ {
+        EOF => "end of file".to_string(),
+        _ => format!("`{}`", typ.spelling(names)),
+    }
+}
+
+fn describe_one(typ: &TokenType, names: &Interner) -> String {
+    match *typ {
+        ID(id) if id.is_empty() => "identifier".to_string(),
+        NUM(num) if num.is_empty() => "number".to_string(),
+        _ => describe_found(typ, names),
+    }
+}
@@ -0,0 +1,58 @@
+// Identifiers and numbers are interned, each distinct spelling is given a
+// Symbol and everything else holds that, so comparing two names is
+// comparing two integers
+// Every Scanner has an Interner of its own, which the Program parsed from
+// it carries along, a Symbol only means anything to the Interner it came from
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+#[derive(Debug, Clone)]
+pub struct Interner {
+    symbols: HashMap<String, Symbol>,
+    names: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        let mut interner = Interner {
+            symbols: HashMap::new(),
+            names: vec![],
+        };
+        interner.intern("");
+        interner
+    }
+
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(name) {
+            return symbol;
+        }
+        let symbol = Symbol(self.names.len() as u32);
+        self.names.push(name.to_string());
+        self.symbols.insert(name.to_string(), symbol);
+        symbol
+    }
+
+    // How symbol is spelt
+    pub fn name(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+}
+
+impl Default for Interner {
+    fn default() -> Interner {
+        Interner::new()
+    }
+}
+
+impl Symbol {
+    // The empty spelling, which stands for any identifier or number in
+    // the sets of tokens the grammar expects
+    pub const EMPTY: Symbol = Symbol(0);
+
+    pub fn is_empty(self) -> bool {
+        self == Symbol::EMPTY
+    }
+}
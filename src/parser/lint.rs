@@ -0,0 +1,140 @@
+// Checks for truth values and numbers getting mixed up
+// Comparisons and connectives give 1 or 0, which arithmetic will happily
+// take, and and, or and not will take any number, treating all but 0 as
+// true, but either is more often a slip than meant
+// Runs over the syntax tree of a program that parsed without errors
+
+use super::ast::{BinOp, Block, Expr, ExprKind, GuardedCommands, Program, Stmt, StmtKind, UnOp};
+use super::diagnostic::{Diagnostic, MIXED_TRUTH};
+
+// Warnings for every operand of the wrong kind
+pub fn check(program: &Program) -> Vec<Diagnostic> {
+    let mut warnings = vec![];
+    block(&program.block, &mut warnings);
+    warnings
+}
+
+fn block(block: &Block, warnings: &mut Vec<Diagnostic>) {
+    for stmt in &block.stmts {
+        statement(stmt, warnings);
+    }
+}
+
+fn statement(stmt: &Stmt, warnings: &mut Vec<Diagnostic>) {
+    match stmt.kind {
+        StmtKind::Assign { ref value, .. } => expr(value, warnings),
+        StmtKind::Print(ref value) => expr(value, warnings),
+        StmtKind::If(ref gcs) | StmtKind::Do(ref gcs) => guarded_commands(gcs, warnings),
+        StmtKind::Fa { ref from, ref to, ref st, ref body, .. } => {
+            expr(from, warnings);
+            expr(to, warnings);
+            if let Some(ref st) = *st {
+                expr(st, warnings);
+            }
+            block(body, warnings);
+        }
+    }
+}
+
+// A guard on its own may be a number, as it always could
+fn guarded_commands(gcs: &GuardedCommands, warnings: &mut Vec<Diagnostic>) {
+    for gc in &gcs.arms {
+        expr(&gc.guard, warnings);
+        block(&gc.body, warnings);
+    }
+    if let Some(ref otherwise) = gcs.otherwise {
+        block(otherwise, warnings);
+    }
+}
+
+fn expr(e: &Expr, warnings: &mut Vec<Diagnostic>) {
+    match e.kind {
+        ExprKind::Var(_) | ExprKind::Num(_) => {}
+        ExprKind::Binary(op, ref lhs, ref rhs) => {
+            for operand in &[lhs, rhs] {
+                operand_of(e, spelling(op), wants_truth(op), operand, warnings);
+                expr(operand, warnings);
+            }
+        }
+        ExprKind::Unary(op, ref operand) => {
+            let (name, truth) = match op {
+                UnOp::Square => ("^", false),
+                UnOp::Sqrt => ("@", false),
+                UnOp::Neg => ("-", false),
+                UnOp::Plus => ("+", false),
+                UnOp::Not => ("not", true),
+            };
+            operand_of(e, name, Some(truth), operand, warnings);
+            expr(operand, warnings);
+        }
+    }
+}
+
+// Check operand against what the operator in parent wants, a truth value
+// or a number, or either if wants_truth is None
+fn operand_of(parent: &Expr,
+              name: &str,
+              wants_truth: Option<bool>,
+              operand: &Expr,
+              warnings: &mut Vec<Diagnostic>) {
+    let warning = match (wants_truth, is_truth(operand)) {
+        (Some(true), false) => {
+            Diagnostic::warning(MIXED_TRUTH,
+                                format!("Number used as a truth value by `{}`", name),
+                                operand.span)
+                .with_note("every number other than 0 counts as true".to_string())
+                .with_help("compare it, as in x /= 0, to say which numbers are meant".to_string())
+        }
+        (Some(false), true) => {
+            Diagnostic::warning(MIXED_TRUTH,
+                                format!("Truth value used as a number by `{}`", name),
+                                operand.span)
+                .with_note("a comparison or connective gives 1 when it holds and 0 when it does \
+                            not"
+                    .to_string())
+        }
+        _ => return,
+    };
+    warnings.push(warning.with_label(parent.span, format!("the operand of this `{}`", name)));
+}
+
+// Whether e gives 1 or 0 for true or false rather than a number
+fn is_truth(e: &Expr) -> bool {
+    match e.kind {
+        ExprKind::Binary(op, _, _) => wants_truth(op) != Some(false),
+        ExprKind::Unary(UnOp::Not, _) => true,
+        _ => false,
+    }
+}
+
+// Some(true) for and and or, which want truth values, Some(false) for
+// arithmetic, which wants numbers, and None for comparisons, which take
+// either and give a truth value
+fn wants_truth(op: BinOp) -> Option<bool> {
+    match op {
+        BinOp::Or | BinOp::And => Some(true),
+        BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Mod | BinOp::Pow => {
+            Some(false)
+        }
+        BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge => None,
+    }
+}
+
+fn spelling(op: BinOp) -> &'static str {
+    match op {
+        BinOp::Or => "or",
+        BinOp::And => "and",
+        BinOp::Eq => "=",
+        BinOp::Ne => "/=",
+        BinOp::Lt => "<",
+        BinOp::Gt => ">",
+        BinOp::Le => "<=",
+        BinOp::Ge => ">=",
+        BinOp::Add => "+",
+        BinOp::Sub => "-",
+        BinOp::Mul => "*",
+        BinOp::Div => "/",
+        BinOp::Mod => "mod",
+        BinOp::Pow => "**",
+    }
+}
@@ -1,418 +1,1026 @@
+pub mod ast;
+pub mod definite;
+pub mod diagnostic;
+pub mod grammar;
+pub mod interner;
+pub mod lint;
+pub mod render;
+pub mod sarif;
 pub mod scanner;
+pub mod suggest;
 pub mod token;
 pub mod symbol_table;
 
+use self::ast::{BinOp, Block, Decl, Expr, ExprKind, GuardedCommand, GuardedCommands, Ident,
+                Program, Span, Stmt, StmtKind, UnOp};
+use self::diagnostic::Diagnostic;
+use self::diagnostic::{BAD_NUMBER, JUNK, MISPLACED_DECLARATION, NUMBER_RANGE, REDECLARED,
+                       SYNTAX, TOO_MANY_ERRORS, UNDECLARED, UNSUPPORTED};
+use self::interner::{Interner, Symbol};
 use self::scanner::Scanner;
 use self::token::{Token, TokenType};
 use self::symbol_table::SymbolTable;
-use std::io::Write;
-use std::io;
+use std::mem;
+
+type ParseResult<T> = Result<T, Diagnostic>;
+
+// Stop reporting after this many errors, the rest are likely fallout
+pub const MAX_ERRORS: usize = 20;
+
+// How many bits the ints programs compute with have
+// Fixed rather than an option, as the C translation declares everything
+// int, helpers included, which is 32 bits on every platform it is
+// compiled for
+pub const INT_BITS: u32 = 32;
 
 pub struct Parser {
     token: Token,
+    // The token before this one, where the last production ended
+    previous: Token,
     scanner: Scanner,
     sym_tab: SymbolTable,
+    errors: Vec<Diagnostic>,
+    warnings: Vec<Diagnostic>,
+    // Warnings about declarations and how variables are used, only given
+    // if the parse succeeds, as after an error they are likely fallout,
+    // e.g. a misspelt rav makes every name after it a declaration
+    var_warnings: Vec<Diagnostic>,
+    max_errors: usize,
+    // Closing keywords of the if / do / fa constructs currently open,
+    // and of those abandoned part way through because of an error
+    closers: Vec<TokenType>,
+    orphans: Vec<TokenType>,
+    // Tokens scanned so far, and how many had been when we last recovered
+    scanned: usize,
+    recovered_at: Option<usize>,
 }
 
 impl Parser {
-    pub fn new(filename: String) -> Parser {
+    // Parse the file filename
+    pub fn new(filename: &str) -> ParseResult<Parser> {
+        Ok(Parser::from_scanner(Scanner::new(filename)?))
+    }
+
+    // Parse text already in memory
+    pub fn from_source(source: &str) -> Parser {
+        Parser::from_scanner(Scanner::from_source(source))
+    }
+
+    // Parse whatever scanner reads, from a file, memory or any io::Read
+    pub fn from_scanner(scanner: Scanner) -> Parser {
         Parser {
             token: Token {
-                line: 0,
                 typ: TokenType::EOF,
+                span: Span::default(),
             },
-            scanner: Scanner::new(&filename),
+            previous: Token {
+                typ: TokenType::EOF,
+                span: Span::default(),
+            },
+            scanner,
             sym_tab: SymbolTable::new(),
+            errors: vec![],
+            warnings: vec![],
+            var_warnings: vec![],
+            max_errors: MAX_ERRORS,
+            closers: vec![],
+            orphans: vec![],
+            scanned: 0,
+            recovered_at: None,
+        }
+    }
+
+    // Give up once this many errors have been reported
+    pub fn set_max_errors(&mut self, max_errors: usize) {
+        self.max_errors = max_errors;
+    }
+
+    // Error at the current token
+    fn error(&self, code: &'static str, msg: String) -> Diagnostic {
+        Diagnostic::error_at(code, msg, &self.token, self.names())
+    }
+
+    // How the identifiers and numbers scanned so far are spelt
+    fn names(&self) -> &Interner {
+        self.scanner.names()
+    }
+
+    // Error for a token that is none of those the grammar allows here
+    // context says where we are, e.g. "after `:=`"
+    fn unexpected(&self, expected: &[TokenType], context: &str) -> Diagnostic {
+        let context = match context.is_empty() {
+            true => String::new(),
+            false => format!(" {}", context),
+        };
+        let e = self.error(SYNTAX,
+                           format!("expected {}{}, found {}",
+                                   grammar::describe(expected, self.names()),
+                                   context,
+                                   grammar::describe_found(&self.token.typ, self.names())));
+
+        // An identifier where a keyword belongs is likely that keyword misspelt
+        let keywords: Vec<String> = expected.iter()
+            .filter(|typ| !matches!(**typ, TokenType::ID(_) | TokenType::NUM(_)))
+            .map(|typ| typ.spelling(self.names()))
+            .collect();
+        match self.token.typ {
+            TokenType::ID(id) => {
+                match suggest::closest(self.names().name(id), keywords.iter().map(|k| k.as_ref())) {
+                    Some(keyword) => e.with_help(format!("did you mean `{}`?", keyword)),
+                    None => e,
+                }
+            }
+            _ => e,
         }
     }
 
-    fn error(&self, foo: &str) -> ! {
-        writeln!(&mut io::stderr(), "[ERROR] {}", foo).unwrap();
-        panic!()
+    // Error for the current identifier not being in scope, with whatever
+    // hints we can find as to what was meant
+    fn undeclared(&self, what: &str) -> Diagnostic {
+        let name = self.token.typ.spelling(self.names());
+        let mut e = self.error(UNDECLARED, format!("{} {}", what, name));
+        if let Some(var) = self.sym_tab.out_of_scope(&self.token) {
+            e = e.with_label(var.declared(), format!("`{}` declared here", name));
+            if let Some(ended) = var.ended() {
+                e = e.with_note(format!("`{}` is out of scope, the block it was declared in \
+                                         ended on line {}",
+                                        name,
+                                        ended.line));
+            }
+        }
+        if let Some(keyword) = suggest::keyword(&name) {
+            e = e.with_help(format!("did you mean the keyword `{}`?", keyword));
+        } else if let Some(var) = suggest::closest(&name,
+                                                   self.sym_tab.names_in_scope(self.names())) {
+            e = e.with_help(format!("did you mean `{}`?", var));
+        }
+        e
+    }
+
+    // The usual context for a message, the token we have just gone past
+    fn after_previous(&self) -> String {
+        match self.scanned > 1 {
+            true => format!("after {}", grammar::describe_found(&self.previous.typ, self.names())),
+            false => String::new(),
+        }
+    }
+
+
+    // Record an error and carry on parsing
+    // Only fails once there have been too many errors to keep going
+    fn report(&mut self, diagnostic: Diagnostic) -> ParseResult<()> {
+        self.errors.push(diagnostic);
+        match self.errors.len() >= self.max_errors {
+            true => {
+                Err(Diagnostic::error(TOO_MANY_ERRORS,
+                                      format!("Too many errors, stopping after {}",
+                                              self.errors.len()),
+                                      self.token.span))
+            }
+            false => Ok(()),
+        }
+    }
+
+    // Record a syntax error we are about to skip past
+    // If nothing was consumed since the last recovery it is most likely
+    // fallout from that one, so it is dropped. Constructs left open at the
+    // end of the file are always worth hearing about though
+    fn recover(&mut self, diagnostic: Diagnostic) -> ParseResult<()> {
+        if self.errors.len() >= self.max_errors {
+            return Err(diagnostic);
+        }
+        let at_end = self.token_match(TokenType::EOF);
+        match self.recovered_at == Some(self.scanned) && !at_end {
+            true => Ok(()),
+            false => self.report(diagnostic),
+        }
+    }
+
+    // Skip tokens until one of stop, a statement starter, the closer of
+    // an open construct, or the end of file
+    fn synchronize(&mut self, stop: &[TokenType]) -> ParseResult<()> {
+        while !stop.contains(&self.token.typ) && !self.is_statement_start() &&
+              !self.closes_construct() && !self.token_match(TokenType::EOF) {
+            self.scan()?;
+        }
+        self.recovered_at = Some(self.scanned);
+        Ok(())
     }
 
     // Parse through the file given to the provided Scanner to tokenize
-    pub fn parse(&mut self) {
-        self.scan();
-        self.program();
+    // Every error found is returned, not just the first
+    pub fn parse(&mut self) -> Result<Program, Vec<Diagnostic>> {
+        let program = self.scan().and_then(|_| self.program());
+        let program = match program {
+            Ok(program) => program,
+            Err(e) => {
+                self.errors.push(e);
+                return Err(self.errors.clone());
+            }
+        };
 
         if !self.token_match(TokenType::EOF) {
-            writeln!(&mut io::stderr(),
-                     "[ERROR] Junk after logical end of program")
-                .unwrap();
-            panic!();
+            self.errors.push(self.error(JUNK, "Junk after logical end of program".to_string()));
+        }
+
+        match self.errors.is_empty() {
+            true => {
+                self.warnings.append(&mut self.var_warnings);
+                self.warnings.extend(definite::check(&program));
+                self.warnings.extend(lint::check(&program));
+                Ok(program)
+            }
+            false => Err(self.errors.clone()),
         }
+    }
+
+    // Problems that do not stop translation, found by the last parse
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    // The text being parsed, for showing diagnostics in context
+    pub fn source(&self) -> &str {
+        self.scanner.source()
+    }
 
-        self.sym_tab.display_variables();
+    // Variables seen while parsing, along with their uses and assignments
+    pub fn symbol_table(&self) -> &SymbolTable {
+        &self.sym_tab
     }
 
     // program ::= block
-    fn program(&mut self) {
-        println!("#include <stdio.h>\n");
-        println!("int main()\n{{");
-        self.block();
-        println!("return 0;\n}}");
+    fn program(&mut self) -> ParseResult<Program> {
+        Ok(Program {
+            block: self.block()?,
+            names: self.names().clone(),
+        })
     }
 
     // block ::= [declarations] statement_list
-    fn block(&mut self) {
+    fn block(&mut self) -> ParseResult<Block> {
+        let span = self.token.span;
         self.sym_tab.add_frame();
-        if self.token_match(TokenType::VAR) {
-            self.declarations();
-        }
-        self.statement_list();
-        self.sym_tab.pop_frame();
+        let decls = match self.token_match(TokenType::VAR) {
+            true => self.recovering_declarations()?,
+            false => vec![],
+        };
+        let stmts = self.statement_list()?;
+        let unused = self.sym_tab.pop_frame(self.token.span, self.scanner.names());
+        self.var_warnings.extend(unused);
+        Ok(Block {
+            decls,
+            stmts,
+            span: self.since(span),
+        })
     }
 
     // declarations ::= "var" { id } "rav"
-    fn declarations(&mut self) {
-        self.must_be(TokenType::VAR);
-        while self.token_match(TokenType::ID("".to_string())) {
+    fn declarations(&mut self) -> ParseResult<Vec<Decl>> {
+        let mut decls = vec![];
+        self.must_be(TokenType::VAR)?;
+        while self.token_match(TokenType::ID(Symbol::EMPTY)) {
             match self.sym_tab.declared_in_block(&self.token) {
-                true => println!("[WARNING] Redeclared variable {:?}", self.token),
-                false => {
-                    match self.token.typ {
-                        TokenType::ID(ref id) => println!("int x_{}=-12345;", id),
-                        _ => unreachable!(),
-                    };
+                Some(previous) => {
+                    let warning = Diagnostic::warning_at(REDECLARED,
+                                                         format!("Redeclared variable {}",
+                                                                 self.token
+                                                                     .typ
+                                                                     .spelling(self.names())),
+                                                         &self.token,
+                                                         self.names())
+                        .with_label(previous, "previously declared here".to_string());
+                    self.var_warnings.push(warning);
+                }
+                None => {
                     self.sym_tab.add_var(&self.token);
+                    decls.push(Decl { name: self.ident()? });
                 }
             };
-            self.scan();
+            self.scan()?;
+        }
+        self.must_be_in(TokenType::RAV,
+                        &[TokenType::ID(Symbol::EMPTY), TokenType::RAV],
+                        "in declarations")
+            .map_err(|e| {
+                // var a b rva   is a missing rav, not a variable named rva
+                let names = self.names();
+                match decls.last().map(|decl| (decl.name.span, names.name(decl.name.name))) {
+                    Some((span, name)) if suggest::keyword(name) == Some("rav") => {
+                        e.with_label(span, "declared as a variable".to_string())
+                            .with_help(format!("did you mean `rav` instead of `{}`?", name))
+                    }
+                    _ => e,
+                }
+            })?;
+        Ok(decls)
+    }
+
+    // A broken declaration list is skipped up to its "rav"
+    fn recovering_declarations(&mut self) -> ParseResult<Vec<Decl>> {
+        match self.declarations() {
+            Ok(decls) => Ok(decls),
+            Err(e) => {
+                self.recover(e)?;
+                self.synchronize(&[TokenType::RAV])?;
+                if self.token_match(TokenType::RAV) {
+                    self.scan()?;
+                }
+                Ok(vec![])
+            }
         }
-        self.must_be(TokenType::RAV);
     }
 
     // { statement }
-    fn statement_list(&mut self) {
-        while self.is_statement() {
-            self.statement();
+    // A broken statement is skipped up to the next statement or the
+    // closer of whatever construct we are inside of
+    fn statement_list(&mut self) -> ParseResult<Vec<Stmt>> {
+        let mut stmts = vec![];
+        loop {
+            if self.is_statement() {
+                match self.statement() {
+                    Ok(stmt) => stmts.push(stmt),
+                    Err(e) => {
+                        self.recover(e)?;
+                        self.synchronize(&[])?;
+                    }
+                }
+            } else if self.is_orphan() {
+                self.scan()?;
+            } else if self.token_match(TokenType::VAR) {
+                // Still declare them, so later uses are not reported as well
+                let e = self.error(MISPLACED_DECLARATION,
+                                   "Declarations must come before the statements of a block"
+                                       .to_string());
+                self.report(e)?;
+                self.recovering_declarations()?;
+            } else if !self.ends_block() {
+                let e = self.unexpected(&self.statement_follow(), &self.after_previous());
+                self.recover(e)?;
+                self.synchronize(&[])?;
+            } else {
+                break;
+            }
         }
+        Ok(stmts)
     }
 
     // statement ::= assignment | if | do | fa | print
-    fn statement(&mut self) {
-        match self.token.typ {
-            TokenType::ID(_) => self.assignment(),
-            TokenType::IF => self.eif(),
-            TokenType::DO => self.edo(),
-            TokenType::FA => self.fa(),
-            TokenType::PRINT => self.print(),
-            _ => self.error("statement"),
+    fn statement(&mut self) -> ParseResult<Stmt> {
+        let span = self.token.span;
+        let kind = match self.token.typ {
+            TokenType::ID(_) => self.assignment()?,
+            TokenType::IF => self.construct(TokenType::FI, Parser::eif)?,
+            TokenType::DO => self.construct(TokenType::OD, Parser::edo)?,
+            TokenType::FA => self.construct(TokenType::AF, Parser::fa)?,
+            TokenType::PRINT => self.print()?,
+            _ => return Err(self.unexpected(grammar::STATEMENT, "")),
         };
+        Ok(Stmt {
+            kind,
+            span: self.since(span),
+        })
+    }
+
+    // Parse an if / do / fa, remembering which keyword will close it
+    fn construct(&mut self,
+                 closer: TokenType,
+                 production: fn(&mut Parser) -> ParseResult<StmtKind>)
+                 -> ParseResult<StmtKind> {
+        self.closers.push(closer);
+        let kind = production(self);
+        if let Some(closer) = self.closers.pop() {
+            if kind.is_err() {
+                self.orphans.push(closer);
+            }
+        }
+        kind
     }
 
     // assignment ::= id ":=" expression
-    fn assignment(&mut self) {
-        if !self.sym_tab.in_scope(&self.token) {
-            panic!("[ERROR] Assigning to undeclared ID {:?}", self.token);
+    fn assignment(&mut self) -> ParseResult<StmtKind> {
+        match self.sym_tab.in_scope(&self.token) {
+            true => self.sym_tab.inc_assign(&self.token),
+            false => {
+                let e = self.undeclared("Assigning to undeclared ID");
+                self.report(e)?;
+            }
         }
-        self.sym_tab.inc_assign(&self.token);
-        match self.token.typ {
-            TokenType::ID(ref id) => print!("x_{}=", id),
-            _ => unreachable!(),
-        };
+        let target = self.ident()?;
 
-        self.must_be(TokenType::ID("".to_string()));
-        self.must_be(TokenType::ASSIGN);
-        self.expression();
-        println!(";");
+        self.must_be(TokenType::ID(Symbol::EMPTY))?;
+        self.must_be(TokenType::ASSIGN)?;
+        let value = self.expression()?;
+        Ok(StmtKind::Assign { target, value })
     }
 
     // print ::= "print" expression
-    fn print(&mut self) {
-        self.must_be(TokenType::PRINT);
-        print!("printf(\"%d\\n\", ");
-        self.expression();
-        println!(");");
+    fn print(&mut self) -> ParseResult<StmtKind> {
+        self.must_be(TokenType::PRINT)?;
+        Ok(StmtKind::Print(self.expression()?))
     }
 
     // if ::= "if" guarded_commands "fi"
-    fn eif(&mut self) {
-        print!("\nif");
-        self.must_be(TokenType::IF);
-        self.guarded_commands();
-        self.must_be(TokenType::FI);
+    fn eif(&mut self) -> ParseResult<StmtKind> {
+        let opener = self.token.clone();
+        self.must_be(TokenType::IF)?;
+        let gcs = self.guarded_commands(&opener)?;
+        self.close_guarded_commands(&opener, TokenType::FI, &gcs)?;
+        Ok(StmtKind::If(gcs))
     }
 
     // do ::= "do" guarded_commands "od"
-    fn edo(&mut self) {
-        println!("while(1){{");
-        self.must_be(TokenType::DO);
-        print!("if");
-        self.guarded_commands();
-        println!("else {{ break; }}");
-        println!("}}\n");
-        self.must_be(TokenType::OD);
-    }
-
-    // fa ::= "fa" id ":=" expression "to" expression ["st" expression] commands "af"
-    fn fa(&mut self) {
-        print!("for( ");
-        self.must_be(TokenType::FA);
-        if !self.sym_tab.in_scope(&self.token) {
-            panic!("[ERROR] Reference to undeclared ID {:?}", self.token);
-        }
-        let name: String = match self.token.typ {
-            TokenType::ID(ref id) => id.chars().collect(),
-            _ => unreachable!(),
-        };
-        print!("x_{}", name);
-        self.sym_tab.inc_assign(&self.token);
-        self.must_be(TokenType::ID("".to_string()));
-        print!(" = ");
-        self.must_be(TokenType::ASSIGN);
-        self.expression();
-        print!("; x_{} <= ", name);
-        self.must_be(TokenType::TO);
-        self.expression();
-        println!("; x_{}++ )", name);
+    // where the guarded commands have no else
+    fn edo(&mut self) -> ParseResult<StmtKind> {
+        let opener = self.token.clone();
+        self.must_be(TokenType::DO)?;
+        let gcs = self.guarded_commands(&opener)?;
+        self.close_guarded_commands(&opener, TokenType::OD, &gcs)?;
+        Ok(StmtKind::Do(gcs))
+    }
 
-        if self.token_match(TokenType::ST) {
-            print!("if");
-            self.must_be(TokenType::ST);
-            self.expression();
+    // The "fi" or "od" after guarded commands, which could have been
+    // followed by more of them until an else was seen
+    fn close_guarded_commands(&mut self,
+                              opener: &Token,
+                              closer: TokenType,
+                              gcs: &GuardedCommands)
+                              -> ParseResult<()> {
+        match gcs.otherwise {
+            Some(_) => self.must_close(opener, closer.clone(), &[closer], "after `else` commands"),
+            None => {
+                let mut expected = vec![closer.clone()];
+                expected.extend_from_slice(grammar::more_guards(&closer));
+                self.must_close(opener, closer, &expected, "after guarded command")
+            }
         }
+    }
 
-        self.commands();
-        self.must_be(TokenType::AF);
+    // fa ::= "fa" id ":=" expression "to" expression ["st" condition] commands "af"
+    fn fa(&mut self) -> ParseResult<StmtKind> {
+        let opener = self.token.clone();
+        self.must_be(TokenType::FA)?;
+        let var = self.ident()?;
+        match self.sym_tab.in_scope(&self.token) {
+            true => self.sym_tab.inc_loop_control(&self.token),
+            false => {
+                let e = self.undeclared("Reference to undeclared ID");
+                self.report(e)?;
+            }
+        }
+        self.must_be(TokenType::ID(Symbol::EMPTY))?;
+        self.must_be(TokenType::ASSIGN)?;
+        let from = self.expression()?;
+        self.must_be_in(TokenType::TO,
+                        &grammar::after_expression(&[TokenType::TO]),
+                        &self.after_previous())?;
+        let to = self.expression()?;
+
+        let st = match self.token_match(TokenType::ST) {
+            true => {
+                self.must_be(TokenType::ST)?;
+                Some(self.condition()?)
+            }
+            false => None,
+        };
+
+        let after = match st {
+            Some(_) => grammar::after_condition(&[TokenType::ARROW]),
+            None => grammar::after_expression(&[TokenType::ST, TokenType::ARROW]),
+        };
+        self.must_be_in(TokenType::ARROW, &after, &self.after_previous())?;
+        let body = self.block()?;
+        self.must_close(&opener, TokenType::AF, &[TokenType::AF], "after body")?;
+        Ok(StmtKind::Fa {
+            var,
+            from,
+            to,
+            st,
+            body,
+        })
     }
 
     // guarded_commands ::= guarded_command { "[]" guarded_command } [ "else" commands ]
-    fn guarded_commands(&mut self) {
-        self.guarded_command();
+    // opener is the "if" or "do" they belong to
+    fn guarded_commands(&mut self, opener: &Token) -> ParseResult<GuardedCommands> {
+        let mut arms = vec![self.guarded_command()?];
         while self.token_match(TokenType::BOX) {
-            print!("else if");
-            self.must_be(TokenType::BOX);
-            self.guarded_command();
+            self.must_be(TokenType::BOX)?;
+            arms.push(self.guarded_command()?);
         }
 
-        if self.token_match(TokenType::ELSE) {
-            print!("else");
-            self.must_be(TokenType::ELSE);
-            self.commands();
-        }
+        let otherwise = match self.token_match(TokenType::ELSE) {
+            true => {
+                // Reported, but the commands are still checked
+                if opener.typ == TokenType::DO {
+                    let e = self.error(SYNTAX, "A `do` cannot have an `else`".to_string())
+                        .with_label(opener.span, "in this `do`".to_string())
+                        .with_note("the else would run whenever no guard is true, so the loop \
+                                    could never end"
+                            .to_string());
+                    self.report(e)?;
+                }
+                self.must_be(TokenType::ELSE)?;
+                Some(self.commands()?)
+            }
+            false => None,
+        };
+        Ok(GuardedCommands { arms, otherwise })
     }
 
-    // guarded_command ::= expression commands
-    fn guarded_command(&mut self) {
-        self.expression();
-        self.commands();
+    // guarded_command ::= condition commands
+    // A broken guard is skipped up to its "->" so the body still gets checked
+    fn guarded_command(&mut self) -> ParseResult<GuardedCommand> {
+        let span = self.token.span;
+        let guard = match self.condition().and_then(|guard| {
+            let after = self.after_previous();
+            self.must_be_in(TokenType::ARROW,
+                            &grammar::after_condition(&[TokenType::ARROW]),
+                            &after)?;
+            Ok(guard)
+        }) {
+            Ok(guard) => guard,
+            Err(e) => {
+                self.recover(e)?;
+                self.synchronize(&[TokenType::ARROW])?;
+                if self.token_match(TokenType::ARROW) {
+                    self.scan()?;
+                }
+                // Never generated from, the program has errors
+                Expr {
+                    kind: ExprKind::Num(0),
+                    span,
+                }
+            }
+        };
+        let body = self.block()?;
+        Ok(GuardedCommand {
+            guard,
+            body,
+            span: self.since(span),
+        })
     }
 
     // commands ::= "->" block
-    fn commands(&mut self) {
-        self.must_be(TokenType::ARROW);
-        println!("{{");
-        self.block();
-        println!("}}");
+    fn commands(&mut self) -> ParseResult<Block> {
+        self.must_be(TokenType::ARROW)?;
+        self.block()
+    }
+
+    // condition ::= conjunction { "or" conjunction }
+    fn condition(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.conjunction()?;
+        while self.token_match(TokenType::OR) {
+            self.scan()?;
+            let rhs = self.conjunction()?;
+            lhs = binary(BinOp::Or, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    // conjunction ::= negation { "and" negation }
+    fn conjunction(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.negation()?;
+        while self.token_match(TokenType::AND) {
+            self.scan()?;
+            let rhs = self.negation()?;
+            lhs = binary(BinOp::And, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    // negation ::= "not" negation | expression
+    fn negation(&mut self) -> ParseResult<Expr> {
+        let span = self.token.span;
+        match self.token.typ {
+            TokenType::NOT => {
+                self.scan()?;
+                let operand = self.negation()?;
+                Ok(Expr {
+                    kind: ExprKind::Unary(UnOp::Not, Box::new(operand)),
+                    span: self.since(span),
+                })
+            }
+            _ if self.is_expression() => self.expression(),
+            _ => Err(self.unexpected(grammar::CONDITION, &self.after_previous())),
+        }
     }
 
     // expression ::= simple [relop simple]
-    fn expression(&mut self) {
-        print!("( ");
-        self.simple();
-        if self.is_relop() {
-            self.relop();
-            self.simple();
+    fn expression(&mut self) -> ParseResult<Expr> {
+        let lhs = self.simple()?;
+        match self.is_relop() {
+            true => {
+                let op = self.relop()?;
+                let rhs = self.simple()?;
+                Ok(binary(op, lhs, rhs))
+            }
+            false => Ok(lhs),
         }
-        print!(" )");
     }
 
     // simple ::= term {addop term}
-    fn simple(&mut self) {
-        self.term();
+    fn simple(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.term()?;
         while self.is_addop() {
-            self.addop();
-            self.term();
+            let op = self.addop()?;
+            let rhs = self.term()?;
+            lhs = binary(op, lhs, rhs);
         }
+        Ok(lhs)
     }
 
-    // term ::= factor { multop factor }
-    fn term(&mut self) {
-        self.factor();
+    // term ::= power { multop power }
+    fn term(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.power()?;
         while self.is_multop() {
-            self.multop();
-            self.factor();
+            let op = self.multop()?;
+            let rhs = self.power()?;
+            lhs = binary(op, lhs, rhs);
         }
+        Ok(lhs)
     }
 
-    // factor ::= "(" expression ")" | id | number | "^" expression | "@" expression
-    // TODO : Implement sqrt and power operators
-    fn factor(&mut self) {
+    // power ::= factor [ "**" power ]
+    // Groups to the right, so a ** b ** c is a ** (b ** c)
+    fn power(&mut self) -> ParseResult<Expr> {
+        let lhs = self.factor()?;
+        match self.token_match(TokenType::POWER) {
+            true => {
+                self.scan()?;
+                let rhs = self.power()?;
+                Ok(binary(BinOp::Pow, lhs, rhs))
+            }
+            false => Ok(lhs),
+        }
+    }
+
+    // factor ::= "(" condition ")" | id | number | "^" expression | "@" expression
+    //          | "-" power | "+" power
+    fn factor(&mut self) -> ParseResult<Expr> {
+        let span = self.token.span;
+        let negative_number = self.negative_number();
         match self.token.typ {
             TokenType::ID(_) => {
-                if !self.sym_tab.in_scope(&self.token) {
-                    panic!("[ERROR] Reference to undeclared variable {:?}", self.token);
+                match self.sym_tab.in_scope(&self.token) {
+                    true => self.sym_tab.inc_usage(&self.token),
+                    false => {
+                        let e = self.undeclared("Reference to undeclared variable");
+                        self.report(e)?;
+                    }
                 }
-                let str: String = match self.token.typ {
-                    TokenType::ID(ref id) => id.chars().collect(),
-                    _ => unreachable!(), 
-                };
-                print!("x_{}", str);
-                self.sym_tab.inc_usage(&self.token);
-                self.must_be(TokenType::ID("".to_string()));
-            }
-            TokenType::NUM(_) => {
-                let str: String = match self.token.typ {
-                    TokenType::NUM(ref num) => num.chars().collect(),
-                    _ => unreachable!(), 
-                };
-                print!("{}", str);
-                self.must_be(TokenType::NUM("".to_string()));
+                let id = self.ident()?;
+                self.must_be(TokenType::ID(Symbol::EMPTY))?;
+                Ok(Expr {
+                    kind: ExprKind::Var(id),
+                    span,
+                })
             }
+            TokenType::NUM(_) => self.number(span, false),
             TokenType::LPAREN => {
-                self.must_be(TokenType::LPAREN);
-                print!("( ");
-                self.expression();
-                self.must_be(TokenType::RPAREN);
-                print!(" )");
-            } 
-            _ => self.error("factor"),
-        };
-    }
-
-    // relop ::= "=" | "<" | ">" | "/=" | "<=" | ">="
-    fn relop(&mut self) {
-        match self.token.typ {
-            TokenType::EQ => {
-                self.must_be(TokenType::EQ);
-                print!(" == ");
+                self.must_be(TokenType::LPAREN)?;
+                let mut expr = self.condition()?;
+                self.must_be_in(TokenType::RPAREN,
+                                &grammar::after_condition(&[TokenType::RPAREN]),
+                                &self.after_previous())?;
+                expr.span = self.since(span);
+                Ok(expr)
             }
-            TokenType::LT => {
-                self.must_be(TokenType::LT);
-                print!(" < ");
+            // The operand runs to the end of the expression, so ^ a + 1
+            // squares a + 1
+            TokenType::SQUARE | TokenType::SQRT => {
+                let op = match self.token.typ {
+                    TokenType::SQUARE => UnOp::Square,
+                    _ => UnOp::Sqrt,
+                };
+                self.scan()?;
+                let operand = self.expression()?;
+                Ok(Expr {
+                    kind: ExprKind::Unary(op, Box::new(operand)),
+                    span: self.since(span),
+                })
             }
-            TokenType::GT => {
-                self.must_be(TokenType::GT);
-                print!(" > ");
+            TokenType::MINUS if negative_number => {
+                self.scan()?;
+                self.number(span, true)
             }
-            TokenType::NE => {
-                self.must_be(TokenType::NE);
-                print!(" != ");
+            // Binds tighter than anything but **, so - a * b is (- a) * b
+            // and - a ** 2 is - (a ** 2)
+            TokenType::MINUS | TokenType::PLUS => {
+                let op = match self.token.typ {
+                    TokenType::MINUS => UnOp::Neg,
+                    _ => UnOp::Plus,
+                };
+                self.scan()?;
+                let operand = self.power()?;
+                Ok(Expr {
+                    kind: ExprKind::Unary(op, Box::new(operand)),
+                    span: self.since(span),
+                })
             }
-            TokenType::LE => {
-                self.must_be(TokenType::LE);
-                print!(" <= ");
+            _ => Err(self.unexpected(grammar::EXPRESSION, &self.after_previous())),
+        }
+    }
+
+    // number, negated if a minus came straight before it, which has to
+    // fit in an int
+    // A number that does not is reported, and taken to be 0 to carry on
+    fn number(&mut self, span: Span, negative: bool) -> ParseResult<Expr> {
+        let text = match self.token.typ {
+            TokenType::NUM(num) => self.names().name(num).to_string(),
+            _ => unreachable!(),
+        };
+        // How big the most negative int is
+        let limit = 1u64 << (INT_BITS - 1);
+        let value = match literal(&text) {
+            Ok(value) if value < limit || (negative && value == limit) => value as i64,
+            Ok(_) => {
+                let e = self.error(NUMBER_RANGE,
+                                   format!("Number {}{} does not fit in an int",
+                                           if negative { "-" } else { "" },
+                                           text))
+                    .with_note(format!("ints are {} bits, from -{} to {}",
+                                       INT_BITS,
+                                       limit,
+                                       limit - 1));
+                self.report(e)?;
+                0
             }
-            TokenType::GE => {
-                self.must_be(TokenType::GE);
-                print!(" >= ");
+            Err(problem) => {
+                let e = self.error(BAD_NUMBER, format!("`{}` is not a valid number", text))
+                    .with_note(problem.to_string());
+                self.report(e)?;
+                0
             }
-            _ => self.error("relop"),
         };
+        self.must_be(TokenType::NUM(Symbol::EMPTY))?;
+        Ok(Expr {
+            kind: ExprKind::Num(if negative { -value } else { value }),
+            span: self.since(span),
+        })
+    }
+
+    // Whether the current token is a minus belonging to the number after
+    // it, so that the most negative int can be written
+    // Not when the number is raised to a power, as - 2 ** 2 is - (2 ** 2)
+    fn negative_number(&mut self) -> bool {
+        if !self.token_match(TokenType::MINUS) {
+            return false;
+        }
+        let next = self.scanner.peek().map(|token| token.typ.clone());
+        let after = self.scanner.peek_nth(1).map(|token| token.typ.clone());
+        matches!(next, Some(TokenType::NUM(_))) && after != Some(TokenType::POWER)
+    }
+
+    // relop ::= "=" | "<" | ">" | "/=" | "<=" | ">="
+    fn relop(&mut self) -> ParseResult<BinOp> {
+        let op = match self.token.typ {
+            TokenType::EQ => BinOp::Eq,
+            TokenType::LT => BinOp::Lt,
+            TokenType::GT => BinOp::Gt,
+            TokenType::NE => BinOp::Ne,
+            TokenType::LE => BinOp::Le,
+            TokenType::GE => BinOp::Ge,
+            _ => return Err(self.unexpected(grammar::RELOPS, &self.after_previous())),
+        };
+        self.scan()?;
+        Ok(op)
     }
 
     // addop ::= "+" | "-"
-    fn addop(&mut self) {
-        match self.token.typ {
-            TokenType::PLUS => {
-                self.must_be(TokenType::PLUS);
-                print!(" + ");
-            }
-            TokenType::MINUS => {
-                self.must_be(TokenType::MINUS);
-                print!(" - ");
-            }
-            _ => self.error("addop"),
+    fn addop(&mut self) -> ParseResult<BinOp> {
+        let op = match self.token.typ {
+            TokenType::PLUS => BinOp::Add,
+            TokenType::MINUS => BinOp::Sub,
+            _ => return Err(self.unexpected(grammar::ADDOPS, &self.after_previous())),
         };
+        self.scan()?;
+        Ok(op)
     }
 
-    // multop ::= "*" | "/"
-    fn multop(&mut self) {
-        match self.token.typ {
-            TokenType::TIMES => {
-                self.must_be(TokenType::TIMES);
-                print!(" * ");
-            }
-            TokenType::DIVIDE => {
-                self.must_be(TokenType::DIVIDE);
-                print!(" / ");
-            }
-            _ => self.error("multop"),
+    // multop ::= "*" | "/" | "mod" | "%"
+    fn multop(&mut self) -> ParseResult<BinOp> {
+        let op = match self.token.typ {
+            TokenType::TIMES => BinOp::Mul,
+            TokenType::DIVIDE => BinOp::Div,
+            TokenType::MOD | TokenType::PERCENT => BinOp::Mod,
+            _ => return Err(self.unexpected(grammar::MULTOPS, &self.after_previous())),
         };
+        self.scan()?;
+        Ok(op)
     }
 
-    // Just to avoid having to type out self.scanner.scan()
-    fn scan(&mut self) {
-        self.token = self.scanner.scan();
+    // Move on to the next token from the scanner, staying on EOF once
+    // the scanner has run out
+    // A malformed token or a character outside the language is reported
+    // and skipped over
+    fn scan(&mut self) -> ParseResult<()> {
+        loop {
+            let token = self.scanner.next();
+            for e in self.scanner.take_errors() {
+                self.report(e)?;
+            }
+            let token = match token {
+                Some(Token { typ: TokenType::UNSUP(ch), span }) => {
+                    let message = format!("`{}` is not part of the language", ch.escape_debug());
+                    self.report(Diagnostic::error(UNSUPPORTED, message, span))?;
+                    continue;
+                }
+                Some(token) => token,
+                None => self.token.clone(),
+            };
+            self.previous = mem::replace(&mut self.token, token);
+            self.scanned += 1;
+            return Ok(());
+        }
     }
 
-    fn is_multop(&self) -> bool {
-        match self.token.typ {
-            TokenType::DIVIDE | TokenType::TIMES => true,
-            _ => false,
+    // Span from the token at start up to the last one consumed
+    // Empty, just before start, if nothing has been consumed since
+    fn since(&self, start: Span) -> Span {
+        match self.previous.span.start >= start.start {
+            true => start.to(self.previous.span),
+            false => Span { end: start.start, ..start },
         }
     }
 
-    fn is_relop(&self) -> bool {
+    // Build an Ident from the current token, which must be an ID
+    fn ident(&self) -> ParseResult<Ident> {
         match self.token.typ {
-            TokenType::NE |
-            TokenType::LT |
-            TokenType::GT |
-            TokenType::EQ |
-            TokenType::LE |
-            TokenType::GE => true,
-            _ => false,
+            TokenType::ID(id) => {
+                Ok(Ident {
+                    name: id,
+                    span: self.token.span,
+                })
+            }
+            _ => Err(self.unexpected(&[TokenType::ID(Symbol::EMPTY)], &self.after_previous())),
         }
     }
 
+    fn is_multop(&self) -> bool {
+        matches!(self.token.typ,
+                 TokenType::DIVIDE | TokenType::TIMES | TokenType::MOD | TokenType::PERCENT)
+    }
+
+    fn is_relop(&self) -> bool {
+        matches!(self.token.typ,
+                 TokenType::NE | TokenType::LT | TokenType::GT | TokenType::EQ |
+                 TokenType::LE | TokenType::GE)
+    }
+
     fn is_addop(&self) -> bool {
-        match self.token.typ {
-            TokenType::PLUS | TokenType::MINUS => true,
-            _ => false,
-        }
+        matches!(self.token.typ, TokenType::PLUS | TokenType::MINUS)
+    }
+
+    fn is_expression(&self) -> bool {
+        matches!(self.token.typ,
+                 TokenType::ID(_) | TokenType::NUM(_) | TokenType::LPAREN | TokenType::SQUARE |
+                 TokenType::SQRT | TokenType::MINUS | TokenType::PLUS)
     }
 
     fn is_statement(&self) -> bool {
-        match self.token.typ {
-            TokenType::ID(_) | TokenType::PRINT | TokenType::IF | TokenType::DO | TokenType::FA => {
+        matches!(self.token.typ,
+                 TokenType::ID(_) | TokenType::PRINT | TokenType::IF | TokenType::DO |
+                 TokenType::FA)
+    }
+
+    // What could have come next where a statement was expected: another
+    // statement, the end of the block, or more of an expression if the
+    // last statement ended in one
+    fn statement_follow(&self) -> Vec<TokenType> {
+        let mut set = match self.previous.typ {
+            TokenType::ID(_) | TokenType::NUM(_) | TokenType::RPAREN => grammar::operators(),
+            _ => vec![],
+        };
+        set.extend_from_slice(grammar::STATEMENT);
+        match self.closers.last() {
+            Some(closer) => {
+                set.push(closer.clone());
+                if *closer != TokenType::AF {
+                    set.extend_from_slice(grammar::more_guards(closer));
+                }
+            }
+            None => set.push(TokenType::EOF),
+        }
+        set
+    }
+
+    // Tokens that may follow a block
+    fn ends_block(&self) -> bool {
+        matches!(self.token.typ,
+                 TokenType::FI | TokenType::OD | TokenType::AF | TokenType::BOX |
+                 TokenType::ELSE | TokenType::EOF)
+    }
+
+    // Keywords that can only begin a statement, good places to resume after an error
+    fn is_statement_start(&self) -> bool {
+        matches!(self.token.typ,
+                 TokenType::PRINT | TokenType::IF | TokenType::DO | TokenType::FA)
+    }
+
+    // Whether the current token is the closer of a construct we already gave up on
+    // Those were reported along with the construct, so are quietly dropped
+    fn is_orphan(&mut self) -> bool {
+        if self.closers.contains(&self.token.typ) {
+            return false;
+        }
+        match self.orphans.iter().rposition(|closer| *closer == self.token.typ) {
+            Some(i) => {
+                self.orphans.remove(i);
                 true
             }
+            None => false,
+        }
+    }
+
+    // Whether the current token closes, or continues, the innermost open construct
+    // Stray closers left over from a broken construct are not counted
+    fn closes_construct(&self) -> bool {
+        match self.token.typ {
+            TokenType::FI | TokenType::OD | TokenType::AF => self.closers.contains(&self.token.typ),
+            TokenType::BOX | TokenType::ELSE => {
+                matches!(self.closers.last(), Some(&TokenType::FI) | Some(&TokenType::OD))
+            }
             _ => false,
         }
     }
 
     // Checks if current TokenType is equal to that specified
-    fn must_be(&mut self, typ: TokenType) {
-        match typ {
-            TokenType::ID(_) => {
-                match self.token.typ {
-                    TokenType::ID(_) => self.scan(),
-                    _ => self.error("ID"),
-                }
-            }
-            TokenType::NUM(_) => {
-                match self.token.typ {
-                    TokenType::NUM(_) => self.scan(),
-                    _ => self.error("NUM"),
-                }
-            }
-            _ => {
-                match self.token.typ == typ {
-                    true => self.scan(),
-                    false => self.error("Something"),
-                }
-            }
-        };
+    fn must_be(&mut self, typ: TokenType) -> ParseResult<()> {
+        let context = self.after_previous();
+        self.must_be_in(typ.clone(), &[typ], &context)
+    }
+
+    // Checks the current token is typ, if not say it should have been one of expected
+    fn must_be_in(&mut self,
+                  typ: TokenType,
+                  expected: &[TokenType],
+                  context: &str)
+                  -> ParseResult<()> {
+        match self.token_match(typ) {
+            true => self.scan(),
+            false => Err(self.unexpected(expected, context)),
+        }
+    }
+
+    // Expect the keyword closing the construct begun by opener
+    // If it is missing, point back at where the construct began
+    fn must_close(&mut self,
+                  opener: &Token,
+                  closer: TokenType,
+                  expected: &[TokenType],
+                  context: &str)
+                  -> ParseResult<()> {
+        let opened = opener.typ.spelling(self.names());
+        let context = format!("{} of the `{}` on line {}", context, opened, opener.span.line);
+        self.must_be_in(closer, expected, &context).map_err(|e| {
+            e.with_label(opener.span, format!("`{}` opened here", opened))
+        })
     }
 
     // Return true if current token is of specified type, false if not
     fn token_match(&self, typ: TokenType) -> bool {
         match typ {
-            TokenType::ID(_) => {
-                match self.token.typ {
-                    TokenType::ID(_) => true,
-                    _ => false,
-                }
-            }
-            TokenType::NUM(_) => {
-                match self.token.typ {
-                    TokenType::NUM(_) => true,
-                    _ => false,
-                }
-            }
+            TokenType::ID(_) => matches!(self.token.typ, TokenType::ID(_)),
+            TokenType::NUM(_) => matches!(self.token.typ, TokenType::NUM(_)),
             _ => self.token.typ == typ,
         }
     }
 }
+
+// The value of a number as written, in decimal, or in hex after 0x or
+// binary after 0b, with leading zeros and any _ among the digits ignored
+// Too big a number comes out as u64::MAX rather than wrapping around
+fn literal(text: &str) -> Result<u64, &'static str> {
+    let (radix, digits) = match text.get(..2) {
+        Some("0x") | Some("0X") => (16, &text[2..]),
+        Some("0b") | Some("0B") => (2, &text[2..]),
+        _ => (10, text),
+    };
+    if digits.ends_with('_') {
+        return Err("a `_` has to be followed by more digits");
+    }
+    let mut value: u64 = 0;
+    let mut any = false;
+    for ch in digits.chars().filter(|&ch| ch != '_') {
+        let digit = match ch.to_digit(radix) {
+            Some(digit) => digit,
+            None => return Err("only 0 to 9 are decimal digits"),
+        };
+        value = value.saturating_mul(radix as u64).saturating_add(digit as u64);
+        any = true;
+    }
+    match any {
+        true => Ok(value),
+        false => Err("there are no digits after the 0x or 0b"),
+    }
+}
+
+// Combine two operands into a binary expression, spanning both
+fn binary(op: BinOp, lhs: Expr, rhs: Expr) -> Expr {
+    let span = lhs.span.to(rhs.span);
+    Expr {
+        kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+        span,
+    }
+}
@@ -0,0 +1,158 @@
+// Shows a Diagnostic the way a person wants to read it: which file and
+// where, the offending source line, and a caret under the exact spot
+
+use super::diagnostic::{Diagnostic, Severity};
+use super::token::Span;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// Tabs are shown as this many spaces so carets line up
+const TAB_WIDTH: usize = 4;
+
+pub struct Renderer<'a> {
+    filename: &'a str,
+    source: &'a str,
+    colour: bool,
+}
+
+// One underlined span in a rendered snippet
+struct Mark<'a> {
+    span: Span,
+    underline: char,
+    message: &'a str,
+    colour: &'static str,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(filename: &'a str, source: &'a str, colour: bool) -> Renderer<'a> {
+        Renderer {
+            filename,
+            source,
+            colour,
+        }
+    }
+
+    // error[E003]: Reference to undeclared variable z
+    //   --> input/t33.e:15:11
+    //    |
+    // 15 |     print z
+    //    |           ^
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let colour = match diagnostic.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        let mut out = format!("{}{}[{}]{}{}: {}{}\n",
+                              self.paint(colour),
+                              diagnostic.severity.name(),
+                              diagnostic.code,
+                              self.paint(RESET),
+                              self.paint(BOLD),
+                              diagnostic.message,
+                              self.paint(RESET));
+        if diagnostic.span.line == 0 {
+            return out;
+        }
+
+        let mut marks = vec![Mark {
+                                 span: diagnostic.span,
+                                 underline: '^',
+                                 message: "",
+                                 colour,
+                             }];
+        for label in &diagnostic.labels {
+            marks.push(Mark {
+                span: label.span,
+                underline: '-',
+                message: &label.message,
+                colour: BLUE,
+            });
+        }
+        marks.sort_by_key(|mark| (mark.span.line, mark.span.col));
+
+        let width = marks.iter().map(|mark| mark.span.line.to_string().len()).max().unwrap_or(1);
+        let gutter = format!("{}{:w$} |{}", self.paint(BLUE), "", self.paint(RESET), w = width);
+        out.push_str(&format!("{}{:w$}--> {}{}:{}:{}\n",
+                              self.paint(BLUE),
+                              "",
+                              self.paint(RESET),
+                              self.filename,
+                              diagnostic.span.line,
+                              diagnostic.span.col,
+                              w = width));
+        out.push_str(&gutter);
+        out.push('\n');
+
+        let mut shown = 0;
+        for mark in &marks {
+            let (start, text) = self.line_of(mark.span);
+            if mark.span.line != shown {
+                out.push_str(&format!("{}{:>w$} |{} {}\n",
+                                      self.paint(BLUE),
+                                      mark.span.line,
+                                      self.paint(RESET),
+                                      expand_tabs(text),
+                                      w = width));
+                shown = mark.span.line;
+            }
+
+            // Only underline as much of the span as is on this line
+            let from = mark.span.start - start;
+            let to = (mark.span.end.max(mark.span.start) - start).min(text.len());
+            let indent = expand_tabs(&text[..from]).chars().count();
+            let length = expand_tabs(&text[from..to.max(from)]).chars().count().max(1);
+            let underline: String = (0..length).map(|_| mark.underline).collect();
+            out.push_str(&format!("{} {:indent$}{}{}{}",
+                                  gutter,
+                                  "",
+                                  self.paint(mark.colour),
+                                  underline,
+                                  self.paint(RESET),
+                                  indent = indent));
+            if !mark.message.is_empty() {
+                out.push_str(&format!(" {}{}{}",
+                                      self.paint(mark.colour),
+                                      mark.message,
+                                      self.paint(RESET)));
+            }
+            out.push('\n');
+        }
+
+        let notes = diagnostic.notes.iter().map(|note| ("note", note));
+        for (kind, text) in notes.chain(diagnostic.help.iter().map(|help| ("help", help))) {
+            out.push_str(&format!("{}{:w$} ={} {}{}:{} {}\n",
+                                  self.paint(BLUE),
+                                  "",
+                                  self.paint(RESET),
+                                  self.paint(BOLD),
+                                  kind,
+                                  self.paint(RESET),
+                                  text,
+                                  w = width));
+        }
+        out
+    }
+
+    fn paint(&self, code: &'static str) -> &'static str {
+        match self.colour {
+            true => code,
+            false => "",
+        }
+    }
+
+    // Byte offset where the line holding span begins, and the text of that line
+    fn line_of(&self, span: Span) -> (usize, &'a str) {
+        let at = span.start.min(self.source.len());
+        let start = self.source[..at].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let end = self.source[at..].find('\n').map(|i| i + at).unwrap_or(self.source.len());
+        (start, self.source[start..end].trim_end_matches('\r'))
+    }
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
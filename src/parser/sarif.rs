@@ -0,0 +1,87 @@
+// Diagnostics as a SARIF 2.1.0 log, the format code review and CI
+// tools know how to show inline
+
+use super::diagnostic::{Diagnostic, RULES};
+use super::token::Span;
+use json::Json;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+// One run of the translator over file, whose text is source
+pub fn log(diagnostics: &[Diagnostic], file: &str, source: &str) -> Json {
+    let rules = RULES.iter()
+        .map(|&(id, description)| {
+            Json::Obj(vec![("id", Json::str(id)),
+                           ("shortDescription", Json::Obj(vec![("text", Json::str(description))]))])
+        })
+        .collect();
+    let driver = Json::Obj(vec![("name", Json::str(env!("CARGO_PKG_NAME"))),
+                                ("version", Json::str(env!("CARGO_PKG_VERSION"))),
+                                ("rules", Json::Arr(rules))]);
+    let results = diagnostics.iter().map(|d| result(d, file, source)).collect();
+    let run = Json::Obj(vec![("tool", Json::Obj(vec![("driver", driver)])),
+                             ("results", Json::Arr(results))]);
+    Json::Obj(vec![("$schema", Json::str(SCHEMA)),
+                   ("version", Json::str("2.1.0")),
+                   ("runs", Json::Arr(vec![run]))])
+}
+
+fn result(diagnostic: &Diagnostic, file: &str, source: &str) -> Json {
+    let mut text = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        text.push_str(&format!("\nnote: {}", note));
+    }
+    for help in &diagnostic.help {
+        text.push_str(&format!("\nhelp: {}", help));
+    }
+
+    let mut fields = vec![("ruleId", Json::str(diagnostic.code)),
+                          ("ruleIndex",
+                           Json::num(RULES.iter()
+                               .position(|&(id, _)| id == diagnostic.code)
+                               .unwrap_or(0))),
+                          ("level", Json::str(diagnostic.severity.name())),
+                          ("message", message(text))];
+    if diagnostic.span.line != 0 {
+        fields.push(("locations",
+                     Json::Arr(vec![Json::Obj(vec![("physicalLocation",
+                                                    location(diagnostic.span, file, source))])])));
+    }
+    if !diagnostic.labels.is_empty() {
+        let related = diagnostic.labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                Json::Obj(vec![("id", Json::num(i)),
+                               ("message", message(label.message.clone())),
+                               ("physicalLocation", location(label.span, file, source))])
+            })
+            .collect();
+        fields.push(("relatedLocations", Json::Arr(related)));
+    }
+    Json::Obj(fields)
+}
+
+fn message(text: String) -> Json {
+    Json::Obj(vec![("text", Json::Str(text))])
+}
+
+fn location(span: Span, file: &str, source: &str) -> Json {
+    let (end_line, end_col) = position(source, span.end.max(span.start));
+    let region = Json::Obj(vec![("startLine", Json::num(span.line)),
+                                ("startColumn", Json::num(span.col)),
+                                ("endLine", Json::num(end_line)),
+                                ("endColumn", Json::num(end_col)),
+                                ("byteOffset", Json::num(span.start)),
+                                ("byteLength", Json::num(span.end.saturating_sub(span.start)))]);
+    Json::Obj(vec![("artifactLocation", Json::Obj(vec![("uri", Json::str(file))])),
+                   ("region", region)])
+}
+
+// Line and column, counting from 1, of a byte offset into source
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, before[line_start..].chars().count() + 1)
+}
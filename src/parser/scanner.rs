@@ -1,174 +1,287 @@
-use parser::token::{Token, TokenType, type_for_id};
+use parser::diagnostic;
+use parser::diagnostic::Diagnostic;
+use parser::interner::Interner;
+use parser::token::{Span, Token, TokenType, type_for_id};
 
+use std::collections::VecDeque;
 use std::fs::File;
+use std::io;
 use std::io::Read;
+use std::mem;
 
+// Reads the source one character at a time, never looking more than one
+// character ahead, so scanning takes time in proportion to its length
 pub struct Scanner {
     contents: String,
-    curr_ch: Option<char>,
-    position: usize,
+    // Byte offset of the next character to be read, and where it is
+    offset: usize,
     line: usize,
-    put_back: bool,
+    col: usize,
+    // Tokens scanned ahead of the ones handed out
+    lookahead: VecDeque<Token>,
+    // Whether EOF has been scanned, after which there is nothing more
+    finished: bool,
+    errors: Vec<Diagnostic>,
+    // Spellings of the identifiers and numbers scanned so far
+    names: Interner,
 }
 
 impl Scanner {
     // Create a new scanner, scanning contents of file filename
-    pub fn new(filename: &String) -> Scanner {
-        let mut file = match File::open(&filename) {
-            Ok(f) => f,
-            Err(e) => panic!("Could not open {} : {}", filename, e),
-        };
-        let mut cont = String::new();
-        let _ = file.read_to_string(&mut cont);
+    pub fn new(filename: &str) -> Result<Scanner, Diagnostic> {
+        File::open(filename)
+            .and_then(Scanner::from_reader)
+            .map_err(|e| {
+                Diagnostic::error(diagnostic::IO_ERROR,
+                                  format!("Could not open {} : {}", filename, e),
+                                  Span::default())
+            })
+    }
+
+    // Scan everything that can be read from reader
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<Scanner> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        Ok(Scanner::from_source(&contents))
+    }
+
+    // Scan text already in memory
+    pub fn from_source(source: &str) -> Scanner {
         Scanner {
-            contents: cont,
-            curr_ch: None,
-            position: 0,
+            contents: source.to_string(),
+            offset: 0,
             line: 1,
-            put_back: false,
+            col: 1,
+            lookahead: VecDeque::new(),
+            finished: false,
+            errors: vec![],
+            names: Interner::new(),
         }
     }
 
-    // Advance character by one, DOES NOT set curr_ch
-    fn next_char(&mut self) -> Option<char> {
-        self.position += 1;
-        self.contents.chars().nth(self.position - 1)
+    // The character about to be read, if there is one
+    fn peek_char(&self) -> Option<char> {
+        self.contents[self.offset..].chars().next()
     }
 
-    // Return the next Token in the file
-    // Unsupported characters / EOF are treated as Tokens, no panicing
-    pub fn scan(&mut self) -> Token {
-        match self.put_back {
-            true => {
-                self.put_back = false;
-            }
-            false => {
-                self.curr_ch = self.next_char();
+    // Read the next character, moving on to a new line after a newline
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.peek_char();
+        if let Some(ch) = ch {
+            self.offset += ch.len_utf8();
+            match ch {
+                '\n' => {
+                    self.line += 1;
+                    self.col = 1;
+                }
+                _ => self.col += 1,
             }
         }
+        ch
+    }
+
+    // Where the next character is, as an empty span to start a token from
+    fn mark(&self) -> Span {
+        Span {
+            start: self.offset,
+            end: self.offset,
+            line: self.line,
+            col: self.col,
+        }
+    }
+
+    // Span from start up to the last character read
+    fn span_from(&self, start: Span) -> Span {
+        Span { end: self.offset, ..start }
+    }
+
+    fn token(&self, typ: TokenType, start: Span) -> Token {
+        Token {
+            typ,
+            span: self.span_from(start),
+        }
+    }
+
+    // The text being scanned
+    pub fn source(&self) -> &str {
+        &self.contents
+    }
+
+    // What the identifiers and numbers in the tokens handed out stand for
+    pub fn names(&self) -> &Interner {
+        &self.names
+    }
+
+    // The names, once there is nothing more to scan
+    pub fn into_names(self) -> Interner {
+        self.names
+    }
+
+    // The token the next call to next() will give, scanning ahead if need be
+    pub fn peek(&mut self) -> Option<&Token> {
+        self.peek_nth(0)
+    }
+
+    // The token n places after the one the next call to next() will give,
+    // peek_nth(0) being that one. None only once EOF has been reached
+    pub fn peek_nth(&mut self, n: usize) -> Option<&Token> {
+        self.fill(n + 1);
+        self.lookahead.get(n)
+    }
+
+    // Malformed tokens skipped over since last asked, including any met
+    // while peeking ahead
+    pub fn take_errors(&mut self) -> Vec<Diagnostic> {
+        mem::take(&mut self.errors)
+    }
 
-        match self.curr_ch {
-            None => {
-                Token {
-                    line: self.line,
-                    typ: TokenType::EOF,
+    // Scan until there are n tokens waiting, or there are no more
+    fn fill(&mut self, n: usize) {
+        while self.lookahead.len() < n && !self.finished {
+            match self.scan() {
+                Ok(token) => {
+                    self.finished = token.typ == TokenType::EOF;
+                    self.lookahead.push_back(token);
                 }
+                Err(e) => self.errors.push(e),
             }
-            Some(ch) => {
-                // Chrew through a commented line
-                if ch == '#' {
-                    loop {
-                        let c = self.next_char();
-                        match c {
-                            None => {
-                                return Token {
-                                    line: self.line,
-                                    typ: TokenType::EOF,
-                                }
-                            }
-                            Some(c) => {
-                                if c == '\n' {
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                    self.line += 1;
-                    self.scan()
-                } else if ch == ' ' || ch == '\t' {
-                    self.scan()
-                } else if ch == '\n' {
-                    self.line += 1;
-                    self.scan()
-                } else if ch.is_alphabetic() {
-                    let _id = self.build_val(|c| c.is_alphabetic());
-                    Token {
-                        line: self.line,
-                        typ: type_for_id(_id),
-                    }
-                } else if ch.is_numeric() {
-                    Token {
-                        line: self.line,
-                        typ: TokenType::NUM(self.build_val(|c| c.is_numeric())),
-                    }
-                } else {
-                    Token {
-                        line: self.line,
-                        typ: self.process_special(),
+        }
+    }
+
+    // Return the next Token in the file, skipping whitespace and comments
+    // Unsupported characters / EOF are treated as Tokens, only a malformed
+    // two character operator is an error
+    fn scan(&mut self) -> Result<Token, Diagnostic> {
+        loop {
+            let start = self.mark();
+            let ch = match self.next_char() {
+                None => return Ok(self.token(TokenType::EOF, start)),
+                Some(ch) => ch,
+            };
+            match ch {
+                // Chrew through a commented line, leaving the newline
+                '#' => {
+                    while self.peek_char().is_some_and(|c| c != '\n') {
+                        self.next_char();
                     }
                 }
+                ' ' | '\t' | '\n' => {}
+                _ if ch.is_alphabetic() => {
+                    let span = self.build_val(start, |c| c.is_alphabetic());
+                    let typ = type_for_id(&self.contents[span.start..span.end], &mut self.names);
+                    return Ok(self.token(typ, start));
+                }
+                _ if ch.is_numeric() => {
+                    let span = self.number(ch, start);
+                    let num = self.names.intern(&self.contents[span.start..span.end]);
+                    return Ok(self.token(TokenType::NUM(num), start));
+                }
+                _ => {
+                    let typ = self.process_special(ch, start)?;
+                    return Ok(self.token(typ, start));
+                }
             }
         }
     }
 
     // Match each special character to approprite TokenType
-    fn process_special(&mut self) -> TokenType {
-        let ch = self.curr_ch.unwrap();
-        match ch {
+    fn process_special(&mut self, ch: char, start: Span) -> Result<TokenType, Diagnostic> {
+        Ok(match ch {
             '(' => TokenType::LPAREN,
             ')' => TokenType::RPAREN,
             '=' => TokenType::EQ,
             '+' => TokenType::PLUS,
-            '*' => TokenType::TIMES,
+            '*' => self.next_might_be('*', TokenType::TIMES, TokenType::POWER),
+            '%' => TokenType::PERCENT,
             '@' => TokenType::SQRT,
             '^' => TokenType::SQUARE,
-            '>' => self.next_might_be('=', TokenType::GT, TokenType::LE),
+            '>' => self.next_might_be('=', TokenType::GT, TokenType::GE),
             '-' => self.next_might_be('>', TokenType::MINUS, TokenType::ARROW),
-            '<' => self.next_might_be('=', TokenType::LT, TokenType::GE),
+            '<' => self.next_might_be('=', TokenType::LT, TokenType::LE),
             '/' => self.next_might_be('=', TokenType::DIVIDE, TokenType::NE),
-            ':' => self.next_must_be('=', TokenType::ASSIGN),
-            '[' => self.next_must_be(']', TokenType::BOX),
+            ':' => self.next_must_be(ch, '=', TokenType::ASSIGN, start)?,
+            '[' => self.next_must_be(ch, ']', TokenType::BOX, start)?,
             _ => TokenType::UNSUP(ch),
-        }
+        })
     }
 
     // Current character may or may not be followed by next
     // If it is the two character sequence, return if_two, if not, if_one
     fn next_might_be(&mut self, next: char, if_one: TokenType, if_two: TokenType) -> TokenType {
-        self.curr_ch = self.next_char();
-        match self.curr_ch.unwrap() == next {
-            true => if_two, 
-            false => {
-                self.put_back = true;
-                if_one
+        match self.peek_char() == Some(next) {
+            true => {
+                self.next_char();
+                if_two
             }
+            false => if_one,
         }
     }
 
-    // The current character MUST be followed by next, otherwise we panic
-    fn next_must_be(&mut self, next: char, typ: TokenType) -> TokenType {
-        self.curr_ch = self.next_char();
-        match self.curr_ch.unwrap() == next {
-            true => typ,
-            false => {
-                panic!("[ERROR] On line {}, expected {} found {}",
-                       self.line,
-                       next,
-                       self.curr_ch.unwrap())
+    // The current character, first, MUST be followed by next, otherwise it
+    // is an error
+    fn next_must_be(&mut self,
+                    first: char,
+                    next: char,
+                    typ: TokenType,
+                    start: Span)
+                    -> Result<TokenType, Diagnostic> {
+        match self.peek_char() {
+            Some(ch) if ch == next => {
+                self.next_char();
+                Ok(typ)
+            }
+            // Leave whatever we found to be scanned as the next token
+            found => {
+                let found = match found {
+                    Some('\n') => "end of line".to_string(),
+                    Some(ch) => format!("`{}`", ch),
+                    None => "end of file".to_string(),
+                };
+                Err(Diagnostic::error(diagnostic::BAD_TOKEN,
+                                      format!("expected `{}` after `{}`, found {}",
+                                              next,
+                                              first,
+                                              found),
+                                      self.span_from(start)))
             }
         }
     }
 
-    // Build the value for either an ID or a numeric
-    // Keep taking characters so long as func is true for each
-    fn build_val<F>(&mut self, func: F) -> String
+    // A number, in decimal, or in hex after 0x or binary after 0b, with _
+    // allowed among its digits
+    // Only the Parser works out whether it is a valid number
+    fn number(&mut self, first: char, start: Span) -> Span {
+        let radix = match (first, self.peek_char()) {
+            ('0', Some('x')) | ('0', Some('X')) => 16,
+            ('0', Some('b')) | ('0', Some('B')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.next_char();
+        }
+        self.build_val(start, |c| c.is_digit(radix) || c == '_')
+    }
+
+    // Build the value for either an ID or a numeric, which began at start
+    // Keep taking characters so long as func is true for each, giving back
+    // the span of them all
+    fn build_val<F>(&mut self, start: Span, func: F) -> Span
         where F: Fn(char) -> bool
     {
-
-        let mut id = vec![];
-        loop {
-            let ch = match self.curr_ch {
-                Some(ch) => ch,
-                None => break,
-            };
-            match func(ch) { 
-                true => id.push(ch),
-                false => break,
-            };
-            self.curr_ch = self.next_char();
+        while self.peek_char().is_some_and(&func) {
+            self.next_char();
         }
-        // We've gone one past the ID, so put back last character
-        self.put_back = true;
-        id.iter().map(|c| *c).collect()
+        self.span_from(start)
+    }
+}
+
+// Tokens in source order, ending with EOF
+// Malformed tokens are left out, to be collected with take_errors
+impl Iterator for Scanner {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.fill(1);
+        self.lookahead.pop_front()
     }
 }
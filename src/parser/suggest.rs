@@ -0,0 +1,56 @@
+// Finding what the user probably meant when a name is not known
+
+use super::token::KEYWORDS;
+use std::cmp;
+
+// Optimal string alignment distance: insertions, deletions, substitutions
+// and swapping two neighbouring characters all cost one
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..a.len() + 1 {
+        for j in 1..b.len() + 1 {
+            let cost = match a[i - 1] == b[j - 1] {
+                true => 0,
+                false => 1,
+            };
+            d[i][j] = cmp::min(cmp::min(d[i - 1][j] + 1, d[i][j - 1] + 1),
+                               d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = cmp::min(d[i][j], d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// The candidate closest to name, if any is close enough to be a likely typo
+// Ties go to whichever comes first alphabetically, so the answer never
+// depends on declaration order
+pub fn closest<'a, I>(name: &str, candidates: I) -> Option<&'a str>
+    where I: IntoIterator<Item = &'a str>
+{
+    let limit = cmp::max(name.chars().count() / 3, 1);
+    candidates.into_iter()
+        .filter(|c| *c != name)
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|&(distance, _)| distance <= limit)
+        .min()
+        .map(|(_, c)| c)
+}
+
+// The keyword an identifier is most likely a misspelling of
+// Short identifiers are too close to too many keywords to guess
+pub fn keyword(name: &str) -> Option<&'static str> {
+    match name.chars().count() >= 3 {
+        true => closest(name, KEYWORDS.iter().cloned()),
+        false => None,
+    }
+}
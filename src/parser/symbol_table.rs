@@ -1,139 +1,371 @@
-use super::token::{Token, TokenType};
-use std::collections::HashMap;
-use std::fmt;
+use super::diagnostic::{Diagnostic, NEVER_ASSIGNED, NEVER_READ, UNUSED};
+use super::interner::{Interner, Symbol};
+use super::token::{Span, Token, TokenType};
+use json::Json;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::io::Write;
 
 #[derive(Debug)]
 pub struct Variable {
-    name: String,
-    declared: usize,
-    uses: HashMap<usize, usize>,
-    assignments: HashMap<usize, usize>,
+    name: Symbol,
+    declared: Span,
+    uses: Vec<Span>,
+    assignments: Vec<Span>,
+    // Which block declared it, and how deeply that block is nested
+    scope: usize,
     depth: usize,
+    // Where the block declaring it ended, once it has
+    ended: Option<Span>,
+    // Whether a fa assigns it, and so reads it as well to test for the end
+    // of the loop
+    controls_loop: bool,
 }
 
-// Make it much easier to check for a variable
-impl PartialEq for Variable {
-    fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
+// Formats the report can be written in, see SymbolTable::report
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReportFormat {
+    // As the course had it
+    #[default]
+    Text,
+    Json,
+    Csv,
 }
 
-// Yeah, it sucks, I'll fix it
-impl fmt::Display for Variable {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let _ = write!(f, "{}\n", self.name);
-        let _ = write!(f,
-                       "\tdeclared on {} at depth {}\n\tUses : ",
-                       self.name,
-                       self.depth);
-        for key in self.uses.keys() {
-            let _ = match self.uses.get(key).unwrap() {
-                &1 => write!(f, "{} ", key),
-                _ => write!(f, "{}({}) ", key, self.uses.get(key).unwrap()),
-            };
-        }
-        let _ = write!(f, "\n\tAssignments : ");
-        for key in self.assignments.keys() {
-            let _ = match self.assignments.get(key).unwrap() {
-                &1 => write!(f, "{} ", key),
-                _ => write!(f, "{}({})", key, self.assignments.get(key).unwrap()),
-            };
+// The name of each ReportFormat, as given on the command line
+pub const REPORT_FORMATS: &[&str] = &["text", "json", "csv"];
+
+impl ReportFormat {
+    // One of REPORT_FORMATS, None for anything else
+    pub fn from_name(name: &str) -> Option<ReportFormat> {
+        match name {
+            "text" => Some(ReportFormat::Text),
+            "json" => Some(ReportFormat::Json),
+            "csv" => Some(ReportFormat::Csv),
+            _ => None,
         }
-        write!(f, "\n")
+    }
+}
+
+
+// How many of the spans are on each line, by line
+fn per_line(spans: &[Span]) -> Vec<(usize, usize)> {
+    let mut lines = BTreeMap::new();
+    for span in spans {
+        *lines.entry(span.line).or_insert(0) += 1;
+    }
+    lines.into_iter().collect()
+}
+
+// Lines as "4(2) 5 ", with a count wherever there is more than one
+fn counted(lines: &[(usize, usize)]) -> String {
+    lines.iter()
+        .map(|&(line, count)| {
+            match count {
+                1 => format!("{} ", line),
+                _ => format!("{}({}) ", line, count),
+            }
+        })
+        .collect()
+}
+
+fn counted_json(lines: &[(usize, usize)]) -> Json {
+    Json::Arr(lines.iter()
+        .map(|&(line, count)| {
+            Json::Obj(vec![("line", Json::num(line)), ("count", Json::num(count))])
+        })
+        .collect())
+}
+
+// The lines spans are on, as "line 4" or "lines 4, 5 and 9"
+fn lines(spans: &[Span]) -> String {
+    let lines: Vec<String> = per_line(spans).iter().map(|&(line, _)| line.to_string()).collect();
+    match lines.len() {
+        1 => format!("line {}", lines[0]),
+        n => format!("lines {} and {}", lines[..n - 1].join(", "), lines[n - 1]),
+    }
+}
+
+// The name a token gives a variable
+fn symbol(token: &Token) -> Symbol {
+    match token.typ {
+        TokenType::ID(id) => id,
+        TokenType::NUM(num) => num,
+        _ => panic!("[ERROR] Tried to convert non ID / NUM to variable"),
     }
 }
 
 impl Variable {
     // Cosntruct a Variable from a token
     // Will almost strictly be used
-    pub fn from_token(token: &Token, depth: usize) -> Variable {
+    pub fn from_token(token: &Token, scope: usize, depth: usize) -> Variable {
         Variable {
-            name: match token.typ {
-                TokenType::ID(ref id) => id.chars().collect(),
-                TokenType::NUM(ref num) => num.chars().collect(),
-                _ => panic!("[ERROR] Tried to convert non ID / NUM to variable"),
-            },
-            uses: HashMap::new(),
-            assignments: HashMap::new(),
-            declared: token.line,
-            depth: depth,
+            name: symbol(token),
+            uses: vec![],
+            assignments: vec![],
+            declared: token.span,
+            scope,
+            depth,
+            ended: None,
+            controls_loop: false,
         }
     }
 
-    pub fn inc_usage(&mut self, line: usize) {
-        let uses = self.uses.entry(line).or_insert(0);
-        *uses += 1;
+    pub fn name(&self) -> Symbol {
+        self.name
+    }
+
+    pub fn declared(&self) -> Span {
+        self.declared
+    }
+
+    pub fn scope(&self) -> usize {
+        self.scope
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn ended(&self) -> Option<Span> {
+        self.ended
+    }
+
+    // One entry of the report, in the course's format
+    fn write(&self, names: &Interner, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", names.name(self.name))?;
+        writeln!(out,
+                 "\tdeclared on line {} at depth {}",
+                 self.declared.line,
+                 self.depth)?;
+        writeln!(out, "\tUses : {}", counted(&per_line(&self.uses)))?;
+        writeln!(out, "\tAssignments : {}", counted(&per_line(&self.assignments)))
+    }
+
+    // A warning if the variable is never used, or only ever half used
+    fn pointless(&self, names: &Interner) -> Option<Diagnostic> {
+        let token = Token {
+            typ: TokenType::ID(self.name),
+            span: self.declared,
+        };
+        let (code, problem, spans, done) = match (self.uses.is_empty(),
+                                                  self.assignments.is_empty()) {
+            (false, false) => return None,
+            (true, false) if self.controls_loop => return None,
+            (true, true) => (UNUSED, "never used", &self.uses, ""),
+            (true, false) => (NEVER_READ, "assigned but never read", &self.assignments, "assigned"),
+            (false, true) => (NEVER_ASSIGNED, "read but never assigned", &self.uses, "read"),
+        };
+        let mut warning = Diagnostic::warning_at(code,
+                                                 format!("Variable {} is {}",
+                                                         names.name(self.name),
+                                                         problem),
+                                                 &token,
+                                                 names);
+        if !spans.is_empty() {
+            warning = warning.with_note(format!("{} on {}", done, lines(spans)));
+        }
+        for &span in spans {
+            warning = warning.with_label(span, format!("{} here", done));
+        }
+        Some(warning)
     }
 
-    pub fn inc_assignment(&mut self, line: usize) {
-        let assignments = self.assignments.entry(line).or_insert(0);
-        *assignments += 1;
+    pub fn inc_usage(&mut self, span: Span) {
+        self.uses.push(span);
     }
+
+    pub fn inc_assignment(&mut self, span: Span) {
+        self.assignments.push(span);
+    }
+}
+
+// A block, and the variables it declares
+#[derive(Debug)]
+pub struct Scope {
+    // The block this one is nested in, None for the whole program
+    parent: Option<usize>,
+    depth: usize,
+    bindings: HashMap<Symbol, usize>,
 }
 
+// Every block seen so far, each pointing at the one around it, and every
+// variable declared in them
+// A name is resolved by looking in the innermost open block and then each
+// block around it in turn, so a reference always finds the declaration
+// actually in scope, even when a shadowing one has just gone out of scope
+#[derive(Default)]
 pub struct SymbolTable {
-    frames: Vec<Vec<Variable>>,
+    scopes: Vec<Scope>,
     vars: Vec<Variable>,
-    depth: usize,
+    // The innermost block still open
+    current: Option<usize>,
 }
 
 impl SymbolTable {
     pub fn new() -> SymbolTable {
-        SymbolTable {
-            frames: vec![],
-            vars: vec![],
-            depth: 0,
-        }
+        SymbolTable::default()
     }
 
+    // Enter a new block, nested in the current one
     pub fn add_frame(&mut self) {
-        self.frames.push(vec![]);
-        self.depth += 1;
+        let depth = self.current.map_or(1, |scope| self.scopes[scope].depth + 1);
+        self.scopes.push(Scope {
+            parent: self.current,
+            depth,
+            bindings: HashMap::new(),
+        });
+        self.current = Some(self.scopes.len() - 1);
     }
 
-    pub fn pop_frame(&mut self) {
-        self.frames.pop();
-        self.depth -= 1;
+    // Leave the innermost block, which ends at end
+    // Its variables can no longer be used, so this is when we can tell
+    // which of them were pointless, warnings for those are returned
+    pub fn pop_frame(&mut self, end: Span, names: &Interner) -> Vec<Diagnostic> {
+        let scope = self.current.expect("popped more frames than were added");
+        let mut vars: Vec<usize> = self.scopes[scope].bindings.values().cloned().collect();
+        vars.sort();
+        for &var in &vars {
+            self.vars[var].ended = Some(end);
+        }
+        self.current = self.scopes[scope].parent;
+        vars.iter().filter_map(|&var| self.vars[var].pointless(names)).collect()
     }
 
     pub fn add_var(&mut self, tok: &Token) {
-        self.frames.last_mut().unwrap().push(Variable::from_token(tok, self.depth));
-        self.vars.push(Variable::from_token(tok, self.depth));
+        let scope = self.current.expect("declared a variable outside of any block");
+        let var = Variable::from_token(tok, scope, self.scopes[scope].depth);
+        self.scopes[scope].bindings.insert(var.name, self.vars.len());
+        self.vars.push(var);
     }
 
     pub fn inc_usage(&mut self, tok: &Token) {
-        let var = Variable::from_token(tok, self.depth);
-        for v in self.vars.iter_mut().rev() {
-            if *v == var {
-                v.inc_usage(tok.line);
-                break;
-            }
+        if let Some(var) = self.resolve(symbol(tok)) {
+            self.vars[var].inc_usage(tok.span);
         }
     }
 
     pub fn inc_assign(&mut self, tok: &Token) {
-        let var = Variable::from_token(tok, self.depth);
-        for v in self.vars.iter_mut().rev() {
-            if *v == var {
-                v.inc_assignment(tok.line);
-                break;
+        if let Some(var) = self.resolve(symbol(tok)) {
+            self.vars[var].inc_assignment(tok.span);
+        }
+    }
+
+    // An assignment by the header of a fa
+    pub fn inc_loop_control(&mut self, tok: &Token) {
+        if let Some(var) = self.resolve(symbol(tok)) {
+            self.vars[var].inc_assignment(tok.span);
+            self.vars[var].controls_loop = true;
+        }
+    }
+
+    // The variable name refers to from the current block, if any
+    fn resolve(&self, name: Symbol) -> Option<usize> {
+        let mut scope = self.current;
+        while let Some(s) = scope {
+            if let Some(&var) = self.scopes[s].bindings.get(&name) {
+                return Some(var);
             }
+            scope = self.scopes[s].parent;
         }
+        None
     }
 
     pub fn in_scope(&self, tok: &Token) -> bool {
-        self.frames.iter().any(|frame| (*frame).contains(&Variable::from_token(tok, self.depth)))
+        self.resolve(symbol(tok)).is_some()
+    }
+
+    // Where tok was already declared in the innermost block, if it was
+    pub fn declared_in_block(&self, tok: &Token) -> Option<Span> {
+        let scope = self.current.expect("looked up a variable outside of any block");
+        self.scopes[scope].bindings.get(&symbol(tok)).map(|&var| self.vars[var].declared)
+    }
+
+    // Names of every variable that can be referenced right now
+    pub fn names_in_scope<'a>(&self, names: &'a Interner) -> Vec<&'a str> {
+        let mut in_scope = vec![];
+        let mut scope = self.current;
+        while let Some(s) = scope {
+            in_scope.extend(self.scopes[s].bindings.keys().map(|&name| names.name(name)));
+            scope = self.scopes[s].parent;
+        }
+        in_scope
+    }
+
+    // The latest variable named by tok whose block has already ended
+    pub fn out_of_scope(&self, tok: &Token) -> Option<&Variable> {
+        let name = symbol(tok);
+        self.vars.iter().rev().find(|v| v.name == name && v.ended.is_some())
+    }
+
+    // Every variable, in the order they were declared
+    pub fn variables(&self) -> &[Variable] {
+        &self.vars
+    }
+
+    // Variables in the order the report lists them, by name and then by
+    // where they were declared
+    fn sorted(&self, names: &Interner) -> Vec<&Variable> {
+        let mut vars: Vec<&Variable> = self.vars.iter().collect();
+        vars.sort_by(|a, b| {
+            names.name(a.name)
+                .cmp(names.name(b.name))
+                .then(a.declared.start.cmp(&b.declared.start))
+        });
+        vars
+    }
+
+    // The cross reference report, every variable with where it is used,
+    // in the given format, with the names its variables were interned in
+    pub fn report(&self,
+                  format: ReportFormat,
+                  names: &Interner,
+                  out: &mut dyn Write)
+                  -> io::Result<()> {
+        match format {
+            ReportFormat::Json => writeln!(out, "{}", self.to_json(names)),
+            ReportFormat::Csv => self.write_csv(names, out),
+            ReportFormat::Text => self.display_variables(names, out),
+        }
+    }
+
+    // The report as the course had it
+    pub fn display_variables(&self, names: &Interner, out: &mut dyn Write) -> io::Result<()> {
+        for var in self.sorted(names) {
+            var.write(names, out)?;
+            writeln!(out)?;
+        }
+        Ok(())
     }
 
-    pub fn declared_in_block(&self, tok: &Token) -> bool {
-        let frame = self.frames.last().unwrap();
-        let var = Variable::from_token(tok, self.frames.len());
-        frame.iter().any(|v| *v == var)
+    pub fn to_json(&self, names: &Interner) -> Json {
+        Json::Arr(self.sorted(names)
+            .iter()
+            .map(|var| {
+                Json::Obj(vec![("name", Json::str(names.name(var.name))),
+                               ("declared", Json::num(var.declared.line)),
+                               ("depth", Json::num(var.depth)),
+                               ("scope", Json::num(var.scope)),
+                               ("uses", counted_json(&per_line(&var.uses))),
+                               ("assignments", counted_json(&per_line(&var.assignments)))])
+            })
+            .collect())
     }
 
-    pub fn display_variables(&mut self) {
-        for var in &self.vars {
-            println!("{}", var);
+    // One row per variable, uses and assignments written as in the text
+    // report, 4(2) 5
+    pub fn write_csv(&self, names: &Interner, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "name,declared,depth,scope,uses,assignments")?;
+        for var in self.sorted(names) {
+            writeln!(out,
+                     "{},{},{},{},{},{}",
+                     names.name(var.name),
+                     var.declared.line,
+                     var.depth,
+                     var.scope,
+                     counted(&per_line(&var.uses)).trim_end(),
+                     counted(&per_line(&var.assignments)).trim_end())?;
         }
+        Ok(())
     }
 }
@@ -1,11 +1,41 @@
-#[derive(Debug)]
+use super::interner::{Interner, Symbol};
+
+#[derive(Debug, Clone)]
 pub struct Token {
     pub typ: TokenType,
+    pub span: Span,
+}
+
+// Where something is in the source
+// start and end are byte offsets, end exclusive, line and col of start count from 1
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
     pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    // The smallest span covering both self and other
+    pub fn to(self, other: Span) -> Span {
+        match other.start < self.start {
+            true => other.to(self),
+            false => {
+                Span {
+                    start: self.start,
+                    end: self.end.max(other.end),
+                    line: self.line,
+                    col: self.col,
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[derive(PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum TokenType {
     VAR,
     RAV,
@@ -19,6 +49,10 @@ pub enum TokenType {
     AF,
     TO,
     ST,
+    AND,
+    OR,
+    NOT,
+    MOD,
 
     ASSIGN,
     LPAREN,
@@ -27,6 +61,8 @@ pub enum TokenType {
     MINUS,
     TIMES,
     DIVIDE,
+    PERCENT,
+    POWER,
     SQUARE,
     SQRT,
     EQ,
@@ -39,15 +75,20 @@ pub enum TokenType {
     ARROW,
     BOX,
 
-    ID(String),
-    NUM(String),
+    ID(Symbol),
+    NUM(Symbol),
     EOF,
     UNSUP(char),
 }
 
-// Get the approprite type for a given ID
-pub fn type_for_id(id: String) -> TokenType {
-    match id.as_ref() {
+// Every word type_for_id turns into something other than an ID
+pub const KEYWORDS: &[&str] = &["var", "rav", "print", "if", "fi", "do", "od", "else", "fa", "af",
+                                "to", "st", "and", "or", "not", "mod"];
+
+// Get the approprite type for a given ID, interning it in names if it is
+// not a keyword
+pub fn type_for_id(id: &str, names: &mut Interner) -> TokenType {
+    match id {
         "var" => TokenType::VAR,
         "rav" => TokenType::RAV,
         "print" => TokenType::PRINT,
@@ -60,6 +101,59 @@ pub fn type_for_id(id: String) -> TokenType {
         "af" => TokenType::AF,
         "to" => TokenType::TO,
         "st" => TokenType::ST,
-        _ => TokenType::ID(id),
+        "and" => TokenType::AND,
+        "or" => TokenType::OR,
+        "not" => TokenType::NOT,
+        "mod" => TokenType::MOD,
+        _ => TokenType::ID(names.intern(id)),
+    }
+}
+
+impl TokenType {
+    // How the token was spelled in the source, for messages
+    // Identifiers and numbers are looked up in the names they were interned in
+    pub fn spelling(&self, names: &Interner) -> String {
+        let s = match *self {
+            TokenType::VAR => "var",
+            TokenType::RAV => "rav",
+            TokenType::PRINT => "print",
+            TokenType::IF => "if",
+            TokenType::FI => "fi",
+            TokenType::DO => "do",
+            TokenType::OD => "od",
+            TokenType::ELSE => "else",
+            TokenType::FA => "fa",
+            TokenType::AF => "af",
+            TokenType::TO => "to",
+            TokenType::ST => "st",
+            TokenType::AND => "and",
+            TokenType::OR => "or",
+            TokenType::NOT => "not",
+            TokenType::MOD => "mod",
+            TokenType::ASSIGN => ":=",
+            TokenType::LPAREN => "(",
+            TokenType::RPAREN => ")",
+            TokenType::PLUS => "+",
+            TokenType::MINUS => "-",
+            TokenType::TIMES => "*",
+            TokenType::DIVIDE => "/",
+            TokenType::PERCENT => "%",
+            TokenType::POWER => "**",
+            TokenType::SQUARE => "^",
+            TokenType::SQRT => "@",
+            TokenType::EQ => "=",
+            TokenType::NE => "/=",
+            TokenType::LT => "<",
+            TokenType::GT => ">",
+            TokenType::LE => "<=",
+            TokenType::GE => ">=",
+            TokenType::ARROW => "->",
+            TokenType::BOX => "[]",
+            TokenType::ID(id) => names.name(id),
+            TokenType::NUM(num) => names.name(num),
+            TokenType::EOF => "end of file",
+            TokenType::UNSUP(ch) => return ch.to_string(),
+        };
+        s.to_string()
     }
 }
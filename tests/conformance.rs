@@ -0,0 +1,264 @@
+// Every operator on boundary values, checked by translating to C, compiling
+// and running it, and comparing what it prints with the same arithmetic
+// done here, and what the translation of a program does at run time under
+// each init policy and wherever it should stop or skip something
+// Needs a C compiler, cc or whatever CC names, and passes with a note on
+// stderr if there is none
+
+extern crate parser;
+
+use parser::Options;
+use parser::codegen::{InitPolicy, ModPolicy};
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Around 0 and at either end of int, along with the largest whose square
+// still fits
+const VALUES: &[(i32, &str)] = &[(0, "0"),
+                                 (1, "1"),
+                                 (-1, "-1"),
+                                 (2, "2"),
+                                 (-2, "-2"),
+                                 (3, "3"),
+                                 (-3, "-3"),
+                                 (7, "7"),
+                                 (-7, "-7"),
+                                 (31, "31"),
+                                 (46340, "46340"),
+                                 (-46341, "-46341"),
+                                 (i32::MAX, "2147483647"),
+                                 (i32::MIN, "-2147483648")];
+
+// What a op b should print, None where C leaves it undefined or the
+// program would stop
+type Binary = fn(i32, i32) -> Option<i32>;
+
+fn truth(b: bool) -> Option<i32> {
+    Some(b as i32)
+}
+
+fn floor_mod(a: i32, b: i32) -> Option<i32> {
+    let r = a.checked_rem(b)?;
+    match r != 0 && (r < 0) != (b < 0) {
+        true => Some(r + b),
+        false => Some(r),
+    }
+}
+
+// The largest r with r * r <= a
+fn square_root(a: i32) -> i32 {
+    let mut r = (a as f64).sqrt() as i64;
+    while r * r > a as i64 {
+        r -= 1;
+    }
+    while (r + 1) * (r + 1) <= a as i64 {
+        r += 1;
+    }
+    r as i32
+}
+
+fn power(a: i32, b: i32) -> Option<i32> {
+    match b < 0 {
+        true => None,
+        false => Some(a.wrapping_pow(b as u32)),
+    }
+}
+
+// Every binary operator as spelled in E, and what it does with each mod
+// policy
+fn operators(modulo: &str) -> Vec<(&'static str, Binary)> {
+    let modulo: Binary = match modulo {
+        "floor" => floor_mod,
+        _ => |a: i32, b| a.checked_rem(b),
+    };
+    vec![("=", |a, b| truth(a == b)),
+         ("/=", |a, b| truth(a != b)),
+         ("<", |a, b| truth(a < b)),
+         (">", |a, b| truth(a > b)),
+         ("<=", |a, b| truth(a <= b)),
+         (">=", |a, b| truth(a >= b)),
+         ("+", |a, b| a.checked_add(b)),
+         ("-", |a, b| a.checked_sub(b)),
+         ("*", |a, b| a.checked_mul(b)),
+         ("/", |a, b| a.checked_div(b)),
+         ("mod", modulo),
+         ("%", modulo),
+         ("**", power),
+         ("and", |a, b| truth(a != 0 && b != 0)),
+         ("or", |a, b| truth(a != 0 || b != 0))]
+}
+
+// Each a op b along with what it should print, as one E program
+fn program(modulo: &str) -> (String, Vec<String>) {
+    let mut source = "var a b rav\n".to_string();
+    let mut expected = vec![];
+    for &(a, a_src) in VALUES {
+        source.push_str(&format!("a := {}\n", a_src));
+        if let Some(neg) = a.checked_neg() {
+            source.push_str("print -a\n");
+            expected.push(format!("-{}", a));
+            expected.push(neg.to_string());
+        }
+        source.push_str("print (not a)\n");
+        expected.push(format!("not {}", a));
+        expected.push(((a == 0) as i32).to_string());
+        source.push_str("print ^a\n");
+        expected.push(format!("^{}", a));
+        expected.push(a.wrapping_mul(a).to_string());
+        if a >= 0 {
+            source.push_str("print @a\n");
+            expected.push(format!("@{}", a));
+            expected.push(square_root(a).to_string());
+        }
+        for &(b, b_src) in VALUES {
+            source.push_str(&format!("b := {}\n", b_src));
+            for (op, eval) in operators(modulo) {
+                if let Some(result) = eval(a, b) {
+                    source.push_str(&format!("print (a {} b)\n", op));
+                    expected.push(format!("{} {} {}", a, op, b));
+                    expected.push(result.to_string());
+                }
+            }
+        }
+    }
+    (source, expected)
+}
+
+// Tests run at the same time, so each run gets a directory of its own
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+// Translate, compile and run, None if there is no C compiler
+fn run(source: &str, options: Options) -> Option<Output> {
+    let code = match parser::translate_str(source, options) {
+        Ok(output) => output.code,
+        Err(errors) => panic!("the conformance program does not translate: {}", errors[0]),
+    };
+
+    let dir = env::temp_dir().join(format!("conformance-{}-{}",
+                                           std::process::id(),
+                                           RUNS.fetch_add(1, Ordering::SeqCst)));
+    fs::create_dir_all(&dir).unwrap();
+    let c = dir.join("ops.c");
+    let exe: PathBuf = dir.join("ops");
+    fs::write(&c, code).unwrap();
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let compiled = match Command::new(&cc).arg("-o").arg(&exe).arg(&c).output() {
+        Ok(compiled) => compiled,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("{} not found, skipping the conformance run", cc);
+            return None;
+        }
+        Err(e) => panic!("could not run {}: {}", cc, e),
+    };
+    assert!(compiled.status.success(),
+            "{} failed: {}",
+            cc,
+            String::from_utf8_lossy(&compiled.stderr));
+    let ran = Command::new(&exe).output().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    Some(ran)
+}
+
+fn conforms(modulo: &str) {
+    let (source, expected) = program(modulo);
+    let options = Options {
+        modulo: ModPolicy::from_name(modulo).unwrap(),
+        ..Options::default()
+    };
+    let ran = match run(&source, options) {
+        Some(ran) => ran,
+        None => return,
+    };
+    assert!(ran.status.success(),
+            "the conformance program failed: {}",
+            String::from_utf8_lossy(&ran.stderr));
+    let printed = String::from_utf8(ran.stdout).unwrap();
+    let mut printed = printed.lines();
+    for pair in expected.chunks(2) {
+        let what = &pair[0];
+        let want = &pair[1];
+        match printed.next() {
+            Some(got) => assert_eq!(got, want, "{} with mod {}", what, modulo),
+            None => panic!("nothing printed for {} with mod {}", what, modulo),
+        }
+    }
+    assert_eq!(printed.next(), None, "printed more than expected");
+}
+
+#[test]
+fn operators_conform_with_truncating_mod() {
+    conforms("trunc");
+}
+
+#[test]
+fn operators_conform_with_flooring_mod() {
+    conforms("floor");
+}
+
+#[test]
+fn square_root_of_a_negative_number_stops_the_program() {
+    let ran = match run("var a rav\na := -4\nprint 1\nprint @a\nprint 2\n", Options::default()) {
+        Some(ran) => ran,
+        None => return,
+    };
+    assert!(!ran.status.success());
+    assert_eq!(String::from_utf8(ran.stdout).unwrap(), "1\n");
+    assert_eq!(String::from_utf8(ran.stderr).unwrap(),
+               "square root of negative number -4 on line 4\n");
+}
+
+#[test]
+fn connectives_short_circuit() {
+    let source = "var a b rav\n\
+                  a := 7 b := 0\n\
+                  if b /= 0 and a / b > 1 -> print 1 else -> print 2 fi\n\
+                  if b = 0 or a / b > 1 -> print 3 else -> print 4 fi\n";
+    let ran = match run(source, Options::default()) {
+        Some(ran) => ran,
+        None => return,
+    };
+    assert!(ran.status.success(),
+            "dividing by 0 was not skipped: {}",
+            String::from_utf8_lossy(&ran.stderr));
+    assert_eq!(String::from_utf8(ran.stdout).unwrap(), "2\n3\n");
+}
+
+// What reading a variable before assigning it does with init
+fn read_before_assigned(init: InitPolicy) -> Option<Output> {
+    let options = Options {
+        init,
+        ..Options::default()
+    };
+    run("var a b rav\nb := 1\nprint b\nprint a\nprint 2\n", options)
+}
+
+#[test]
+fn unassigned_variables_start_as_the_sentinel() {
+    if let Some(ran) = read_before_assigned(InitPolicy::Sentinel) {
+        assert!(ran.status.success());
+        assert_eq!(String::from_utf8(ran.stdout).unwrap(), "1\n-12345\n2\n");
+    }
+}
+
+#[test]
+fn unassigned_variables_start_as_zero() {
+    if let Some(ran) = read_before_assigned(InitPolicy::Zero) {
+        assert!(ran.status.success());
+        assert_eq!(String::from_utf8(ran.stdout).unwrap(), "1\n0\n2\n");
+    }
+}
+
+#[test]
+fn reading_an_unassigned_variable_traps() {
+    if let Some(ran) = read_before_assigned(InitPolicy::Trap) {
+        assert_eq!(ran.status.code(), Some(1));
+        assert_eq!(String::from_utf8(ran.stdout).unwrap(), "1\n");
+        assert_eq!(String::from_utf8(ran.stderr).unwrap(),
+                   "uninitialized variable a declared on line 1\n");
+    }
+}
@@ -0,0 +1,129 @@
+// Definite assignment, checked through the warnings a translation gives
+// for reads that may come before any assignment
+
+extern crate parser;
+
+use parser::Options;
+use parser::parser::diagnostic::{MAYBE_UNASSIGNED, NEVER_ASSIGNED, UNASSIGNED};
+
+// The code and line of each warning about reading unassigned variables
+fn flagged(source: &str) -> Vec<(&'static str, usize)> {
+    let output = match parser::translate_str(source, Options::default()) {
+        Ok(output) => output,
+        Err(errors) => panic!("{} does not translate: {}", source, errors[0]),
+    };
+    output.warnings
+        .iter()
+        .filter(|w| [MAYBE_UNASSIGNED, UNASSIGNED, NEVER_ASSIGNED].contains(&w.code))
+        .map(|w| (w.code, w.span.line))
+        .collect()
+}
+
+#[test]
+fn assigned_before_read() {
+    assert_eq!(flagged("var a rav\na := 1\nprint a\n"), vec![]);
+}
+
+#[test]
+fn read_before_assigned() {
+    assert_eq!(flagged("var a rav\nprint a\na := 1\n"), vec![(UNASSIGNED, 2)]);
+}
+
+#[test]
+fn never_assigned_is_only_reported_once() {
+    assert_eq!(flagged("var a rav\nprint a\n"), vec![(NEVER_ASSIGNED, 1)]);
+}
+
+#[test]
+fn do_body_may_not_run() {
+    let source = "var a b rav\n\
+                  b := 0\n\
+                  do b < 1 -> a := 1 b := 1 od\n\
+                  print a\n";
+    assert_eq!(flagged(source), vec![(MAYBE_UNASSIGNED, 4)]);
+}
+
+#[test]
+fn do_body_sees_assignments_from_earlier_rounds() {
+    let source = "var a b rav\n\
+                  b := 0\n\
+                  do b < 2 ->\n\
+                  print a\n\
+                  a := b b := b + 1\n\
+                  od\n";
+    assert_eq!(flagged(source), vec![(MAYBE_UNASSIGNED, 4)]);
+}
+
+#[test]
+fn fa_assigns_its_variable_before_the_first_test() {
+    let source = "var i rav\n\
+                  fa i := 1 to 0 -> print i af\n\
+                  print i\n";
+    assert_eq!(flagged(source), vec![]);
+}
+
+#[test]
+fn fa_bound_is_worked_out_after_its_variable_is_assigned() {
+    assert_eq!(flagged("var i rav\nfa i := 1 to i + 2 -> print i af\n"), vec![]);
+    assert_eq!(flagged("var i rav\nfa i := i to 2 -> print i af\n"),
+               vec![(UNASSIGNED, 2)]);
+}
+
+#[test]
+fn fa_body_may_not_run() {
+    let source = "var i a rav\n\
+                  fa i := 1 to 0 -> a := i af\n\
+                  print a\n";
+    assert_eq!(flagged(source), vec![(MAYBE_UNASSIGNED, 3)]);
+}
+
+#[test]
+fn if_without_else_joins_with_the_state_before_it() {
+    let source = "var a b rav\n\
+                  b := 1\n\
+                  if b = 1 -> a := 1 fi\n\
+                  print a\n";
+    assert_eq!(flagged(source), vec![(MAYBE_UNASSIGNED, 4)]);
+}
+
+#[test]
+fn if_assigning_in_every_arm_and_else() {
+    let source = "var a b rav\n\
+                  b := 1\n\
+                  if b = 1 -> a := 1 [] b = 2 -> a := 2 else -> a := 3 fi\n\
+                  print a\n";
+    assert_eq!(flagged(source), vec![]);
+}
+
+#[test]
+fn nested_loops_report_each_read_once() {
+    let source = "var a b c rav\n\
+                  b := 0\n\
+                  do b < 2 ->\n\
+                  c := 0\n\
+                  do c < 2 ->\n\
+                  print a\n\
+                  c := c + 1\n\
+                  od\n\
+                  b := b + 1\n\
+                  od\n\
+                  a := 1\n\
+                  fa b := 1 to 2 ->\n\
+                  fa c := 1 to 2 ->\n\
+                  print a + b + c\n\
+                  af\n\
+                  af\n";
+    assert_eq!(flagged(source), vec![(UNASSIGNED, 6)]);
+
+    let source = "var a b c rav\n\
+                  b := 0\n\
+                  do b < 2 ->\n\
+                  c := 0\n\
+                  do c < 2 ->\n\
+                  print a\n\
+                  a := c c := c + 1\n\
+                  od\n\
+                  b := b + 1\n\
+                  od\n";
+    assert_eq!(flagged(source), vec![(MAYBE_UNASSIGNED, 6)]);
+}
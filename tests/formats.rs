@@ -0,0 +1,100 @@
+// Diagnostics in the formats tools read, SARIF and a JSON object per line
+
+extern crate parser;
+
+use parser::json::Json;
+use parser::parser::sarif;
+use parser::{Diagnostic, Options};
+
+const UNDECLARED: &str = "var a rav\nif 1 -> var b rav b := 1 fi\na := b\nprint a\n";
+const REDECLARED: &str = "var a a rav\na := 1\nprint a\n";
+
+// Every diagnostic translating source gives
+fn diagnostics(source: &str) -> Vec<Diagnostic> {
+    match parser::translate_str(source, Options::default()) {
+        Ok(output) => output.warnings,
+        Err(diagnostics) => diagnostics,
+    }
+}
+
+// What path leads to in json, keys naming fields of objects and numbers
+// indexing arrays
+fn at<'a>(json: &'a Json, path: &[&str]) -> &'a Json {
+    path.iter().fold(json, |json, key| {
+        match *json {
+            Json::Obj(ref fields) => {
+                match fields.iter().find(|&&(name, _)| name == *key) {
+                    Some((_, value)) => value,
+                    None => panic!("no {} in {}", key, json),
+                }
+            }
+            Json::Arr(ref items) => &items[key.parse::<usize>().unwrap()],
+            _ => panic!("{} is neither an object nor an array", json),
+        }
+    })
+}
+
+// The SARIF log for source, which should have a single result
+fn sarif_log(source: &str) -> Json {
+    let log = sarif::log(&diagnostics(source), "test.e", source);
+    assert_eq!(at(&log, &["version"]).to_string(), "\"2.1.0\"");
+    match *at(&log, &["runs", "0", "results"]) {
+        Json::Arr(ref results) => assert_eq!(results.len(), 1),
+        ref results => panic!("results are not an array but {}", results),
+    }
+    log
+}
+
+// The region of the single character at line, col and byte offset
+fn region(line: usize, col: usize, offset: usize) -> String {
+    format!("{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{},\
+             \"byteOffset\":{},\"byteLength\":1}}",
+            line,
+            col,
+            line,
+            col + 1,
+            offset)
+}
+
+#[test]
+fn sarif_for_an_undeclared_variable() {
+    let log = sarif_log(UNDECLARED);
+    let result = at(&log, &["runs", "0", "results", "0"]);
+    assert_eq!(at(result, &["ruleId"]).to_string(), "\"E003\"");
+    assert_eq!(at(result, &["level"]).to_string(), "\"error\"");
+    let location = at(result, &["locations", "0", "physicalLocation"]);
+    assert_eq!(at(location, &["artifactLocation", "uri"]).to_string(), "\"test.e\"");
+    assert_eq!(at(location, &["region"]).to_string(), region(3, 6, 43));
+    let related = at(result, &["relatedLocations", "0"]);
+    assert_eq!(at(related, &["message", "text"]).to_string(), "\"`b` declared here\"");
+    assert_eq!(at(related, &["physicalLocation", "region"]).to_string(),
+               region(2, 13, 22));
+}
+
+#[test]
+fn sarif_for_a_redeclaration() {
+    let log = sarif_log(REDECLARED);
+    let result = at(&log, &["runs", "0", "results", "0"]);
+    assert_eq!(at(result, &["ruleId"]).to_string(), "\"W001\"");
+    assert_eq!(at(result, &["level"]).to_string(), "\"warning\"");
+    assert_eq!(at(result, &["locations", "0", "physicalLocation", "region"]).to_string(),
+               region(1, 7, 6));
+    let related = at(result, &["relatedLocations", "0"]);
+    assert_eq!(at(related, &["message", "text"]).to_string(),
+               "\"previously declared here\"");
+    assert_eq!(at(related, &["physicalLocation", "region"]).to_string(),
+               region(1, 5, 4));
+}
+
+#[test]
+fn json_for_a_redeclaration() {
+    let diagnostics = diagnostics(REDECLARED);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].to_json("test.e").to_string(),
+               "{\"severity\":\"warning\",\"code\":\"W001\",\
+                \"message\":\"Redeclared variable a\",\"file\":\"test.e\",\
+                \"span\":{\"start\":6,\"end\":7,\"line\":1,\"column\":7},\"token\":\"a\",\
+                \"labels\":[{\"message\":\"previously declared here\",\
+                \"span\":{\"start\":4,\"end\":5,\"line\":1,\"column\":5}}],\
+                \"notes\":[],\"help\":[]}");
+}
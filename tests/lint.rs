@@ -0,0 +1,38 @@
+// The lint for truth values and numbers getting mixed up
+
+extern crate parser;
+
+use parser::parser::diagnostic::MIXED_TRUTH;
+use parser::parser::lint;
+
+// The code and column of each warning the lint gives for source
+fn linted(source: &str) -> Vec<(&'static str, usize)> {
+    let program = match parser::parse_str(source) {
+        Ok(program) => program,
+        Err(errors) => panic!("{} does not parse: {}", source, errors[0]),
+    };
+    lint::check(&program).iter().map(|w| (w.code, w.span.col)).collect()
+}
+
+#[test]
+fn comparison_used_as_a_number() {
+    assert_eq!(linted("var a b rav a := 1 b := 2 print (a < b) + 1"),
+               vec![(MIXED_TRUTH, 33)]);
+}
+
+#[test]
+fn number_used_as_a_truth_value() {
+    assert_eq!(linted("var a rav a := 1 if not a -> print 1 fi"), vec![(MIXED_TRUTH, 25)]);
+}
+
+#[test]
+fn number_as_a_guard_on_its_own() {
+    assert_eq!(linted("var a rav a := 1 if a -> print 1 fi"), vec![]);
+}
+
+#[test]
+fn comparisons_joined_by_connectives() {
+    let source = "var a b c d rav a := 1 b := 2 c := 3 d := 4\n\
+                  if a < b and c > d -> print 1 [] not (a = b) or c /= d -> print 2 fi";
+    assert_eq!(linted(source), vec![]);
+}
@@ -0,0 +1,105 @@
+// Numbers are read into their values by the front end, which rejects any
+// that do not fit in an int
+
+extern crate parser;
+
+use parser::parser::ast::{ExprKind, StmtKind, UnOp};
+use parser::parser::diagnostic::{BAD_NUMBER, NUMBER_RANGE};
+
+// The value of number when printed
+fn value(number: &str) -> i64 {
+    let source = format!("print {}", number);
+    let program = match parser::parse_str(&source) {
+        Ok(program) => program,
+        Err(errors) => panic!("{} does not parse: {}", number, errors[0]),
+    };
+    match program.block.stmts[0].kind {
+        StmtKind::Print(ref expr) => {
+            match expr.kind {
+                ExprKind::Num(value) => value,
+                ref kind => panic!("{} is not a number but {:?}", number, kind),
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+// The code of the only error in print number
+fn error(number: &str) -> &'static str {
+    match parser::parse_str(&format!("print {}", number)) {
+        Ok(_) => panic!("{} should not parse", number),
+        Err(errors) => {
+            assert_eq!(errors.len(), 1, "{} gives {:?}", number, errors);
+            errors[0].code
+        }
+    }
+}
+
+#[test]
+fn decimal() {
+    assert_eq!(value("0"), 0);
+    assert_eq!(value("42"), 42);
+    assert_eq!(value("2147483647"), 2147483647);
+}
+
+#[test]
+fn leading_zeros_are_not_octal() {
+    assert_eq!(value("010"), 10);
+    assert_eq!(value("0009"), 9);
+    assert_eq!(value("0000000000000000000000001"), 1);
+}
+
+#[test]
+fn hex_and_binary() {
+    assert_eq!(value("0x1F"), 31);
+    assert_eq!(value("0XfF"), 255);
+    assert_eq!(value("0b101"), 5);
+    assert_eq!(value("0x7fffffff"), 2147483647);
+}
+
+#[test]
+fn separators() {
+    assert_eq!(value("1_000_000"), 1000000);
+    assert_eq!(value("0b1111_0000"), 240);
+    assert_eq!(value("0x_ff"), 255);
+}
+
+#[test]
+fn minus_belongs_to_the_number() {
+    assert_eq!(value("-5"), -5);
+    assert_eq!(value("-2147483648"), -2147483648);
+    assert_eq!(value("-0x80000000"), -2147483648);
+}
+
+#[test]
+fn out_of_range() {
+    assert_eq!(error("2147483648"), NUMBER_RANGE);
+    assert_eq!(error("-2147483649"), NUMBER_RANGE);
+    assert_eq!(error("0x100000000"), NUMBER_RANGE);
+    assert_eq!(error("098765432109876543210987654321"), NUMBER_RANGE);
+    assert_eq!(error("99999999999999999999999999999999999999999"), NUMBER_RANGE);
+}
+
+#[test]
+fn only_a_bare_number_can_be_the_most_negative() {
+    assert_eq!(error("-(2147483648)"), NUMBER_RANGE);
+    assert_eq!(error("-2147483648 ** 1"), NUMBER_RANGE);
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(error("1_"), BAD_NUMBER);
+    assert_eq!(error("0x"), BAD_NUMBER);
+    assert_eq!(error("0b_"), BAD_NUMBER);
+}
+
+#[test]
+fn minus_before_a_power_is_not_part_of_the_number() {
+    let program = parser::parse_str("print -2 ** 2").unwrap();
+    match program.block.stmts[0].kind {
+        StmtKind::Print(ref expr) => {
+            assert!(matches!(expr.kind, ExprKind::Unary(UnOp::Neg, _)));
+        }
+        _ => unreachable!(),
+    }
+}
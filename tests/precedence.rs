@@ -0,0 +1,223 @@
+// How expressions group, checked on the syntax tree itself and by
+// printing programs back out as E and parsing them again
+
+extern crate parser;
+
+use parser::Options;
+use parser::parser::ast::{BinOp, Expr, ExprKind, Interner, StmtKind, UnOp};
+
+// The expression as fully parenthesized prefix notation, e.g. (+ a (* b c))
+fn sexpr(expr: &Expr, names: &Interner) -> String {
+    match expr.kind {
+        ExprKind::Var(ref id) => names.name(id.name).to_string(),
+        ExprKind::Num(ref num) => num.to_string(),
+        ExprKind::Binary(op, ref lhs, ref rhs) => {
+            let op = match op {
+                BinOp::Or => "or",
+                BinOp::And => "and",
+                BinOp::Eq => "=",
+                BinOp::Ne => "/=",
+                BinOp::Lt => "<",
+                BinOp::Gt => ">",
+                BinOp::Le => "<=",
+                BinOp::Ge => ">=",
+                BinOp::Add => "+",
+                BinOp::Sub => "-",
+                BinOp::Mul => "*",
+                BinOp::Div => "/",
+                BinOp::Mod => "mod",
+                BinOp::Pow => "**",
+            };
+            format!("({} {} {})", op, sexpr(lhs, names), sexpr(rhs, names))
+        }
+        ExprKind::Unary(op, ref operand) => {
+            let op = match op {
+                UnOp::Square => "^",
+                UnOp::Sqrt => "@",
+                UnOp::Neg => "neg",
+                UnOp::Plus => "pos",
+                UnOp::Not => "not",
+            };
+            format!("({} {})", op, sexpr(operand, names))
+        }
+    }
+}
+
+// How the expression in print expression groups
+fn grouping(expression: &str) -> String {
+    let source = format!("var a b c rav a := 1 b := 2 c := 3 print {}", expression);
+    let program = match parser::parse_str(&source) {
+        Ok(program) => program,
+        Err(errors) => panic!("{} does not parse: {}", expression, errors[0]),
+    };
+    match program.block.stmts.last().map(|stmt| &stmt.kind) {
+        Some(StmtKind::Print(expr)) => sexpr(expr, &program.names),
+        _ => panic!("{} is not the last statement", expression),
+    }
+}
+
+// Printing expression as E and parsing that again groups it the same way
+fn round_trips(expression: &str) {
+    let source = format!("var a b c rav a := 1 b := 2 c := 3 print {}", expression);
+    let options = Options {
+        target: "e".to_string(),
+        ..Options::default()
+    };
+    let printed = match parser::translate_str(&source, options) {
+        Ok(output) => output.code,
+        Err(errors) => panic!("{} does not translate: {}", expression, errors[0]),
+    };
+    let reprinted = printed.lines().last().unwrap().trim_start_matches("print ");
+    assert_eq!(grouping(expression),
+               grouping(reprinted),
+               "{} printed as {}",
+               expression,
+               reprinted);
+}
+
+#[test]
+fn multop_binds_tighter_than_addop() {
+    assert_eq!(grouping("a + b * c"), "(+ a (* b c))");
+    assert_eq!(grouping("a * b - c / a"), "(- (* a b) (/ c a))");
+}
+
+#[test]
+fn addop_binds_tighter_than_relop() {
+    assert_eq!(grouping("a + b < c * 2"), "(< (+ a b) (* c 2))");
+}
+
+#[test]
+fn binary_operators_are_left_associative() {
+    assert_eq!(grouping("a - b - c"), "(- (- a b) c)");
+    assert_eq!(grouping("a / b * c"), "(* (/ a b) c)");
+}
+
+#[test]
+fn square_and_root_take_the_rest_of_the_expression() {
+    assert_eq!(grouping("^a + 1"), "(^ (+ a 1))");
+    assert_eq!(grouping("@a * b"), "(@ (* a b))");
+    assert_eq!(grouping("^a < b"), "(^ (< a b))");
+    assert_eq!(grouping("a + ^b * c"), "(+ a (^ (* b c)))");
+    assert_eq!(grouping("a * @b - c"), "(* a (@ (- b c)))");
+}
+
+#[test]
+fn parentheses_limit_square_and_root() {
+    assert_eq!(grouping("(^a) + 1"), "(+ (^ a) 1)");
+    assert_eq!(grouping("(@a) * (^b) - c"), "(- (* (@ a) (^ b)) c)");
+}
+
+#[test]
+fn square_and_root_nest() {
+    assert_eq!(grouping("^@a"), "(^ (@ a))");
+    assert_eq!(grouping("@^a + b"), "(@ (^ (+ a b)))");
+}
+
+#[test]
+fn minus_and_plus_take_one_factor() {
+    assert_eq!(grouping("-a * b"), "(* (neg a) b)");
+    assert_eq!(grouping("a * -b"), "(* a (neg b))");
+    assert_eq!(grouping("-a + -3"), "(+ (neg a) -3)");
+    assert_eq!(grouping("+a - +b"), "(- (pos a) (pos b))");
+    assert_eq!(grouping("-(a + b)"), "(neg (+ a b))");
+    assert_eq!(grouping("- -a"), "(neg (neg a))");
+}
+
+#[test]
+fn minus_binds_tighter_than_relop() {
+    assert_eq!(grouping("-a < b"), "(< (neg a) b)");
+    assert_eq!(grouping("a = -1"), "(= a -1)");
+}
+
+#[test]
+fn minus_and_square() {
+    assert_eq!(grouping("-^a + 1"), "(neg (^ (+ a 1)))");
+    assert_eq!(grouping("^-a + 1"), "(^ (+ (neg a) 1))");
+    assert_eq!(grouping("-(^a) + 1"), "(+ (neg (^ a)) 1)");
+    assert_eq!(grouping("@-a * -b"), "(@ (* (neg a) (neg b)))");
+}
+
+#[test]
+fn mod_is_a_multop() {
+    assert_eq!(grouping("a + b mod c"), "(+ a (mod b c))");
+    assert_eq!(grouping("a * b % c / 2"), "(/ (mod (* a b) c) 2)");
+}
+
+#[test]
+fn power_binds_tightest_and_groups_to_the_right() {
+    assert_eq!(grouping("a * b ** c"), "(* a (** b c))");
+    assert_eq!(grouping("a ** b ** c"), "(** a (** b c))");
+    assert_eq!(grouping("(a ** b) ** c"), "(** (** a b) c)");
+    assert_eq!(grouping("-a ** 2"), "(neg (** a 2))");
+    assert_eq!(grouping("(-a) ** 2"), "(** (neg a) 2)");
+    assert_eq!(grouping("-2 ** 2"), "(neg (** 2 2))");
+    assert_eq!(grouping("(-2) ** 2"), "(** -2 2)");
+    assert_eq!(grouping("a ** -b * c"), "(* (** a (neg b)) c)");
+    assert_eq!(grouping("^a ** 2 + 1"), "(^ (+ (** a 2) 1))");
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(grouping("(a < b or b < c and c < a)"),
+               "(or (< a b) (and (< b c) (< c a)))");
+    assert_eq!(grouping("(a or b or c)"), "(or (or a b) c)");
+}
+
+#[test]
+fn relop_binds_tighter_than_not() {
+    assert_eq!(grouping("(not a < b and c)"), "(and (not (< a b)) c)");
+    assert_eq!(grouping("(not not a = -b)"), "(not (not (= a (neg b))))");
+    assert_eq!(grouping("(not (a and b))"), "(not (and a b))");
+}
+
+#[test]
+fn conditions_are_allowed_in_guards_and_st() {
+    let source = "var a b rav a := 1 b := 2 \
+                  if a < b and not b = 3 or a = 0 -> print a fi \
+                  do a > 0 and (b > 0 or b = -1) -> a := a - 1 od \
+                  fa a := 1 to 9 st not a = b -> print a af";
+    assert!(parser::parse_str(source).is_ok());
+    assert!(parser::parse_str("var a rav a := 1 print a and a").is_err());
+}
+
+#[test]
+fn e_output_keeps_the_grouping() {
+    for expression in &["a + b * c",
+                        "(a + b) * c",
+                        "a - (b - c)",
+                        "^a + 1",
+                        "(^a) + 1",
+                        "a + (^b) + c",
+                        "(@a) * (^b) - c",
+                        "@(a + b) < ^c",
+                        "(a < b) = (b < c)",
+                        "-a * b",
+                        "-(a * b)",
+                        "- -a",
+                        "+a - +b",
+                        "(-^a) + 1",
+                        "-(^a) + 1",
+                        "a - -(b + c)",
+                        "(a < b or b < c and c < a)",
+                        "((a or b) and c)",
+                        "(not (a and b))",
+                        "(not a) + 1",
+                        "(a and b) * c",
+                        "-(not a)",
+                        "^(a or b)",
+                        "(a < b) = (not b)",
+                        "a mod b * c % 2",
+                        "a mod (b * c)",
+                        "a ** b ** c",
+                        "(a ** b) ** c",
+                        "-a ** 2",
+                        "(-a) ** 2",
+                        "a ** -b",
+                        "(^a) ** 2",
+                        "(-2) ** 2",
+                        "-2 ** 2",
+                        "a - -2",
+                        "- -2"] {
+        round_trips(expression);
+    }
+}
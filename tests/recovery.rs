@@ -0,0 +1,47 @@
+// Carrying on after an error to find the rest, up to a limit
+
+extern crate parser;
+
+use parser::parser::diagnostic::{SYNTAX, TOO_MANY_ERRORS, UNDECLARED};
+use parser::{Options, Parser};
+
+// The code and line of every error translating source gives
+fn errors(source: &str, options: Options) -> Vec<(&'static str, usize)> {
+    match parser::translate_str(source, options) {
+        Ok(_) => panic!("{} should not translate", source),
+        Err(diagnostics) => diagnostics.iter().map(|d| (d.code, d.span.line)).collect(),
+    }
+}
+
+#[test]
+fn every_undeclared_variable_in_t41() {
+    assert_eq!(errors(include_str!("../input/t41.e"), Options::default()),
+               vec![(UNDECLARED, 10), (UNDECLARED, 11), (UNDECLARED, 11), (UNDECLARED, 16)]);
+}
+
+#[test]
+fn syntax_errors_in_separate_statements() {
+    let source = "var a rav\na := \nprint a\na := ( 1\nprint a\n";
+    assert_eq!(errors(source, Options::default()), vec![(SYNTAX, 3), (SYNTAX, 5)]);
+}
+
+#[test]
+fn stops_after_max_errors() {
+    let options = Options {
+        max_errors: 2,
+        ..Options::default()
+    };
+    assert_eq!(errors(include_str!("../input/t41.e"), options),
+               vec![(UNDECLARED, 10), (UNDECLARED, 11), (TOO_MANY_ERRORS, 11)]);
+}
+
+#[test]
+fn parser_stops_after_max_errors() {
+    let mut parser = Parser::from_source(include_str!("../input/t41.e"));
+    parser.set_max_errors(1);
+    let codes: Vec<&str> = match parser.parse() {
+        Ok(_) => panic!("t41 should not parse"),
+        Err(errors) => errors.iter().map(|e| e.code).collect(),
+    };
+    assert_eq!(codes, vec![UNDECLARED, TOO_MANY_ERRORS]);
+}
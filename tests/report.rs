@@ -0,0 +1,40 @@
+// The cross reference report, which variable each use and assignment is
+// credited to and the order they are listed in
+
+extern crate parser;
+
+use parser::Options;
+use parser::parser::symbol_table::ReportFormat;
+
+// The report translating source gives, in format
+fn report(source: &str, format: ReportFormat) -> String {
+    let options = Options {
+        report_format: format,
+        ..Options::default()
+    };
+    match parser::translate_str(source, options) {
+        Ok(output) => output.report,
+        Err(errors) => panic!("the program does not translate: {}", errors[0]),
+    }
+}
+
+#[test]
+fn uses_are_credited_to_the_declaration_in_scope() {
+    assert_eq!(report(include_str!("../input/t45.e"), ReportFormat::Csv),
+               "name,declared,depth,scope,uses,assignments\n\
+                a,2,1,0,4(2) 5 6 8 11 13 15 17 26,3\n\
+                a,5,2,2,5,5\n\
+                a,12,3,5,12,12\n\
+                a,19,2,7,22 24,20\n\
+                a,23,3,8,23,23\n");
+}
+
+// Sorted by name, then by where each was declared, whatever order the
+// blocks were parsed and closed in
+#[test]
+fn report_order_in_every_format() {
+    let source = include_str!("../input/t43.e");
+    assert_eq!(report(source, ReportFormat::Text), include_str!("expected/t43.txt"));
+    assert_eq!(report(source, ReportFormat::Json), include_str!("expected/t43.json"));
+    assert_eq!(report(source, ReportFormat::Csv), include_str!("expected/t43.csv"));
+}
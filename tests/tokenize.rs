@@ -0,0 +1,17 @@
+// The tokenizer on its own, as a program embedding it would use it
+
+extern crate parser;
+
+use parser::TokenType;
+
+#[test]
+fn identifiers_and_numbers_are_spelt_as_written() {
+    let (tokens, names) = parser::tokenize("var ab rav ab := 0x1F print ab").unwrap();
+    let spelt: Vec<String> = tokens.iter().map(|token| token.typ.spelling(&names)).collect();
+    assert_eq!(spelt,
+               vec!["var", "ab", "rav", "ab", ":=", "0x1F", "print", "ab", "end of file"]);
+    match (&tokens[1].typ, &tokens[3].typ) {
+        (&TokenType::ID(first), &TokenType::ID(second)) => assert_eq!(first, second),
+        _ => panic!("ab is not scanned as an identifier"),
+    }
+}
@@ -0,0 +1,38 @@
+// Warnings about how variables are declared and used, which the symbol
+// table gives as each block ends
+
+extern crate parser;
+
+use parser::Options;
+use parser::parser::diagnostic::{NEVER_ASSIGNED, REDECLARED, SYNTAX, UNUSED};
+
+// The code, line and column of each warning translating source gives
+fn warned(source: &str) -> Vec<(&'static str, usize, usize)> {
+    let output = match parser::translate_str(source, Options::default()) {
+        Ok(output) => output,
+        Err(errors) => panic!("{} does not translate: {}", source, errors[0]),
+    };
+    output.warnings.iter().map(|w| (w.code, w.span.line, w.span.col)).collect()
+}
+
+#[test]
+fn unused_and_unassigned_variables_in_t07() {
+    assert_eq!(warned(include_str!("../input/t07.e")),
+               vec![(NEVER_ASSIGNED, 3, 5),
+                    (UNUSED, 3, 7),
+                    (REDECLARED, 4, 5),
+                    (NEVER_ASSIGNED, 4, 7),
+                    (UNUSED, 5, 5)]);
+}
+
+#[test]
+fn no_variable_warnings_after_a_syntax_error() {
+    // The misspelt rav makes every name after it a declaration
+    match parser::translate_str("var a b rva\na := 1\nprint a\n", Options::default()) {
+        Ok(_) => panic!("a misspelt rav should not translate"),
+        Err(diagnostics) => {
+            let codes: Vec<&str> = diagnostics.iter().map(|d| d.code).collect();
+            assert_eq!(codes, vec![SYNTAX]);
+        }
+    }
+}